@@ -0,0 +1,709 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fmt::{self, Write};
+
+use crate::pos::Pos;
+use crate::span::Span;
+use crate::src::{self, Source};
+
+/// Severity of a [`Diagnostic`].
+///
+/// Severities are ordered by importance: `Error > Warning > Note > Help`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Severity {
+	/// A problem which prevents further processing.
+	Error,
+
+	/// A problem which does not prevent further processing.
+	Warning,
+
+	/// Additional information.
+	Note,
+
+	/// A suggestion on how to fix a problem.
+	Help,
+}
+
+impl Severity {
+	const fn rank(self) -> u8 {
+		match self {
+			Self::Error => 3,
+			Self::Warning => 2,
+			Self::Note => 1,
+			Self::Help => 0,
+		}
+	}
+}
+
+impl PartialOrd for Severity {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Ord for Severity {
+	fn cmp(&self, other: &Self) -> Ordering {
+		self.rank().cmp(&other.rank())
+	}
+}
+
+impl fmt::Display for Severity {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Self::Error => "error",
+			Self::Warning => "warning",
+			Self::Note => "note",
+			Self::Help => "help",
+		})
+	}
+}
+
+/// Determins how a [`Label`] is displayed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LabelStyle {
+	/// The label marks the cause of the diagnostic.
+	Primary,
+
+	/// The label adds context to the diagnostic.
+	Secondary,
+}
+
+impl LabelStyle {
+	/// Returns the character used to underline the span of a label.
+	const fn mark(self) -> char {
+		match self {
+			Self::Primary => '^',
+			Self::Secondary => '-',
+		}
+	}
+}
+
+/// A message attached to a [`Span`] of a [`Source`](`crate::src::Source`).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Label {
+	/// Span the label points to.
+	pub span: Span,
+
+	/// Message of the label (may be empty).
+	pub message: String,
+
+	/// Style of the label.
+	pub style: LabelStyle,
+}
+
+impl Label {
+	/// Creates a new label with the given style.
+	pub fn new(
+		span: Span,
+		message: impl Into<String>,
+		style: LabelStyle,
+	) -> Self {
+		Self { span, message: message.into(), style }
+	}
+
+	/// Creates a new [`LabelStyle::Primary`] label.
+	pub fn primary(span: Span, message: impl Into<String>) -> Self {
+		Self::new(span, message, LabelStyle::Primary)
+	}
+
+	/// Creates a new [`LabelStyle::Secondary`] label.
+	pub fn secondary(span: Span, message: impl Into<String>) -> Self {
+		Self::new(span, message, LabelStyle::Secondary)
+	}
+}
+
+impl From<(Span, String)> for Label {
+	/// Creates a new [`LabelStyle::Primary`] label.
+	fn from((span, message): (Span, String)) -> Self {
+		Self::primary(span, message)
+	}
+}
+
+/// Options to control how a [`Diagnostic`] is rendered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct RenderOptions {
+	/// Width in characters at which the message of the diagnostic is
+	/// wrapped.
+	///
+	/// Continuation lines are aligned under the start of the message. Source
+	/// lines and labels are never wrapped as this would misplace the markers.
+	pub max_width: Option<usize>,
+}
+
+/// A message for the user about a [`Source`](`crate::src::Source`).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Diagnostic {
+	/// Severity of the diagnostic.
+	pub severity: Severity,
+
+	/// Identifier of the diagnostic (e.g. `E0308`).
+	pub code: Option<String>,
+
+	/// Main message of the diagnostic.
+	pub message: String,
+
+	/// Labels pointing into the source.
+	pub labels: Vec<Label>,
+}
+
+impl Diagnostic {
+	/// Creates a new diagnostic without any labels.
+	pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+		Self {
+			severity,
+			code: None,
+			message: message.into(),
+			labels: Vec::new(),
+		}
+	}
+
+	/// Creates a new [`Severity::Error`] diagnostic with a single primary
+	/// label at the given span.
+	pub fn error_at(span: Span, message: impl Into<String>) -> Self {
+		Self::new(Severity::Error, message)
+			.with_label(Label::primary(span, String::new()))
+	}
+
+	/// Returns `true` if the diagnostic is a [`Severity::Error`].
+	pub fn is_error(&self) -> bool {
+		self.severity == Severity::Error
+	}
+
+	/// Sets the identifier of the diagnostic.
+	#[must_use]
+	pub fn with_code(mut self, code: impl Into<String>) -> Self {
+		self.code = Some(code.into());
+		self
+	}
+
+	/// Adds the given label.
+	#[must_use]
+	pub fn with_label(mut self, label: impl Into<Label>) -> Self {
+		self.labels.push(label.into());
+		self
+	}
+
+	/// Sorts the labels by the start and then the end of their spans.
+	///
+	/// The sort is stable, so labels with the same span keep their order.
+	pub fn sort_labels(&mut self) {
+		self.labels.sort_by_key(|label| (label.span.low, label.span.high));
+	}
+
+	/// Removes labels which are identical (same span, message and style) to a
+	/// previous label.
+	///
+	/// The order of the remaining labels is kept.
+	pub fn dedup_labels(&mut self) {
+		let mut seen = HashSet::new();
+		self.labels.retain(|label| seen.insert(label.clone()));
+	}
+
+	/// Renders the diagnostic in a human readable form.
+	///
+	/// Duplicate labels are removed (see [`Diagnostic::dedup_labels`]) and the
+	/// remaining ones are rendered in the order of
+	/// [`Diagnostic::sort_labels`].
+	///
+	/// Each label is shown below the first line its span covers, spans
+	/// reaching over multiple lines are only underlined on that line. An
+	/// empty span at the end of the source is shown right after the last
+	/// character.
+	pub fn render(&self, source: &Source) -> String {
+		self.render_with(source, &RenderOptions::default())
+	}
+
+	/// Renders the diagnostic like [`Diagnostic::render`] using the given
+	/// options.
+	pub fn render_with(
+		&self,
+		source: &Source,
+		options: &RenderOptions,
+	) -> String {
+		let mut out = String::new();
+
+		let mut diag = self.clone();
+		diag.dedup_labels();
+		diag.sort_labels();
+
+		// Writing to a string can not fail.
+		let _ = diag.write_to(&mut out, source, options);
+
+		out
+	}
+
+	/// Converts the diagnostic into a JSON value for machine consumption.
+	///
+	/// The spans of the labels are resolved to zero indexed locations
+	/// (columns are counted in bytes) after clamping them to the source. The
+	/// origin is the plain path or name of the source and `null` for
+	/// [`Origin::Unknown`](crate::src::Origin::Unknown). The code is `null` if
+	/// the diagnostic has none:
+	///
+	/// ```json
+	/// {
+	///   "severity": "error",
+	///   "code": "E0308",
+	///   "message": "...",
+	///   "labels": [{
+	///     "origin": "src/main.rs",
+	///     "start": { "line": 0, "col": 4 },
+	///     "end": { "line": 0, "col": 5 },
+	///     "message": "..."
+	///   }]
+	/// }
+	/// ```
+	#[cfg(feature = "serde")]
+	#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+	pub fn to_json(&self, source: &Source) -> serde_json::Value {
+		let loc_json = |pos| {
+			let loc = source
+				.pos_to_loc(pos)
+				.expect("Clamped position must be inside the source");
+
+			serde_json::json!({ "line": loc.line, "col": loc.column })
+		};
+
+		let origin = match source.origin() {
+			src::Origin::Path(path) => {
+				Some(path.to_string_lossy().into_owned())
+			}
+			src::Origin::Named(name) => Some(name.clone()),
+			src::Origin::Unknown => None,
+		};
+
+		let labels: Vec<_> = self
+			.labels
+			.iter()
+			.map(|label| {
+				let span = source.clamp_to_source(label.span);
+
+				serde_json::json!({
+					"origin": origin,
+					"start": loc_json(span.low),
+					"end": loc_json(span.high),
+					"message": label.message,
+				})
+			})
+			.collect();
+
+		serde_json::json!({
+			"severity": self.severity.to_string(),
+			"code": self.code,
+			"message": self.message,
+			"labels": labels,
+		})
+	}
+
+	fn write_to(
+		&self,
+		out: &mut String,
+		source: &Source,
+		options: &RenderOptions,
+	) -> fmt::Result {
+		let prefix = match &self.code {
+			Some(code) => format!("{}[{}]: ", self.severity, code),
+			None => format!("{}: ", self.severity),
+		};
+
+		if let Some(max_width) = options.max_width {
+			let indent = prefix.len();
+			let lines = wrap(&self.message, max_width.saturating_sub(indent));
+
+			writeln!(out, "{}{}", prefix, lines[0])?;
+			for line in &lines[1..] {
+				writeln!(out, "{:indent$}{}", "", line)?;
+			}
+		} else {
+			writeln!(out, "{}{}", prefix, self.message)?;
+		}
+
+		let lines: Vec<_> = self
+			.labels
+			.iter()
+			.map(|label| label_line(source, label.span))
+			.collect();
+
+		let Some(max_line) = lines.iter().max() else {
+			return Ok(());
+		};
+		let gutter = (max_line + 1).to_string().len();
+		let pad = "";
+
+		if let Some(loc) = self
+			.labels
+			.iter()
+			.find(|label| label.style == LabelStyle::Primary)
+			.or_else(|| self.labels.first())
+			.and_then(|label| source.pos_to_loc(label_pos(source, label.span)))
+		{
+			writeln!(out, "{pad:gutter$}--> {}:{}", source.origin(), loc)?;
+		}
+		writeln!(out, "{pad:gutter$} |")?;
+
+		let mut last_line = None;
+
+		for (label, &line) in self.labels.iter().zip(&lines) {
+			let text = source.line(line).expect("Label line must exist");
+			let text = text.strip_suffix('\r').unwrap_or(text);
+
+			if last_line != Some(line) {
+				writeln!(out, "{:>gutter$} | {}", line + 1, text)?;
+				last_line = Some(line);
+			}
+
+			let line_start = source
+				.line_span(line)
+				.expect("Label line must exist")
+				.low
+				.as_usize();
+			let low = label_pos(source, label.span).as_usize() - line_start;
+			let high = source.clamp_to_source(label.span).high.as_usize();
+			let high =
+				high.min(line_start + text.len()).saturating_sub(line_start);
+			let marker =
+				src::underline(text, low..high.max(low), label.style.mark());
+
+			if label.message.is_empty() {
+				writeln!(out, "{pad:gutter$} | {}", marker)?;
+			} else {
+				writeln!(out, "{pad:gutter$} | {} {}", marker, label.message)?;
+			}
+		}
+
+		Ok(())
+	}
+}
+
+/// Returns the highest severity of the given diagnostics.
+///
+/// Returns `None` if there are no diagnostics.
+pub fn max_severity(diags: &[Diagnostic]) -> Option<Severity> {
+	diags.iter().map(|diag| diag.severity).max()
+}
+
+/// Splits the text into lines of at most `width` characters.
+///
+/// Lines are only broken at whitespace, words longer than `width` are put on
+/// a line of their own. Consecutive whitespace is collapsed into a single
+/// space. Always returns at least one (possibly empty) line.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+	let mut lines = vec![String::new()];
+
+	for word in text.split_whitespace() {
+		let line = lines.last_mut().expect("There is always a line");
+
+		if line.is_empty() {
+			line.push_str(word);
+		} else if line.chars().count() + 1 + word.chars().count() <= width {
+			line.push(' ');
+			line.push_str(word);
+		} else {
+			lines.push(word.to_owned());
+		}
+	}
+
+	lines
+}
+
+/// Returns the position at which the label for the given span is shown.
+///
+/// An empty span at the end of a source, which ends with a line break, is
+/// moved onto the line break so that it is shown after the last character of
+/// the final line instead of on an empty line.
+fn label_pos(source: &Source, span: Span) -> Pos {
+	let span = source.clamp_to_source(span);
+	let at_eof =
+		span.low == span.high && span.low.as_usize() == source.byte_len();
+
+	match span.low.as_u32().checked_sub(1).map(Pos::from_u32) {
+		Some(newline) if at_eof && source.byte(newline) == Some(b'\n') => {
+			newline
+		}
+		_ => span.low,
+	}
+}
+
+/// Returns the (zero indexed) line on which the label for the span is shown.
+fn label_line(source: &Source, span: Span) -> usize {
+	source
+		.pos_to_loc(label_pos(source, span))
+		.expect("Clamped position must be inside the source")
+		.line
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::src::Origin;
+
+	#[test]
+	fn severity_ord() {
+		assert!(Severity::Error > Severity::Warning);
+		assert!(Severity::Warning > Severity::Note);
+		assert!(Severity::Note > Severity::Help);
+		assert!(Severity::Help < Severity::Error);
+	}
+
+	#[test]
+	fn is_error() {
+		assert!(Diagnostic::new(Severity::Error, "").is_error());
+		assert!(!Diagnostic::new(Severity::Warning, "").is_error());
+	}
+
+	#[test]
+	fn max_severity() {
+		let diags = [
+			Diagnostic::new(Severity::Note, ""),
+			Diagnostic::new(Severity::Warning, ""),
+			Diagnostic::new(Severity::Help, ""),
+		];
+		assert_eq!(super::max_severity(&diags), Some(Severity::Warning));
+
+		let diags = [
+			Diagnostic::new(Severity::Note, ""),
+			Diagnostic::new(Severity::Error, ""),
+		];
+		assert_eq!(super::max_severity(&diags), Some(Severity::Error));
+		assert_eq!(super::max_severity(&[]), None);
+	}
+
+	#[test]
+	fn error_at() {
+		let span = Span::from(4_u32..8);
+		let diag = Diagnostic::error_at(span, "unexpected token");
+
+		assert_eq!(diag.severity, Severity::Error);
+		assert_eq!(diag.message, "unexpected token");
+		assert_eq!(diag.labels, vec![Label::primary(span, "")]);
+	}
+
+	#[test]
+	fn render() {
+		let source = Source::new(
+			Origin::Path("main.rs".into()),
+			"fn main() {\n\tlet x = 1;\n}\n".into(),
+		);
+		let diag = Diagnostic::new(Severity::Warning, "unused variable")
+			.with_label(Label::primary(Span::from(17_u32..18), "not used"))
+			.with_label(Label::secondary(Span::from(13_u32..16), ""));
+
+		assert_eq!(
+			diag.render(&source),
+			"warning: unused variable\n --> main.rs:2:6\n  |\n2 | \tlet x = \
+			 1;\n  | \t---\n  | \t    ^ not used\n"
+		);
+	}
+
+	#[test]
+	fn render_code() {
+		let source = Source::new(Origin::Path("main.rs".into()), "x".into());
+		let diag = Diagnostic::new(Severity::Error, "mismatched types")
+			.with_label(Label::primary(Span::from(0_u32..1), ""));
+
+		assert_eq!(
+			diag.render(&source),
+			"error: mismatched types\n --> main.rs:1:1\n  |\n1 | x\n  | ^\n"
+		);
+		assert_eq!(
+			diag.with_code("E0308").render(&source),
+			"error[E0308]: mismatched types\n --> main.rs:1:1\n  |\n1 | x\n  \
+			 | ^\n"
+		);
+	}
+
+	#[test]
+	fn render_wrapped() {
+		let source = Source::new(Origin::Unknown, "let x = 1".into());
+		let diag = Diagnostic::new(
+			Severity::Error,
+			"expected one of `,`, `;` or `}` after the last statement",
+		)
+		.with_label(Label::primary(Span::from(9_u32..9), "here"));
+		let options = RenderOptions { max_width: Some(30) };
+
+		assert_eq!(
+			diag.render_with(&source, &options),
+			"error: expected one of `,`,\n       `;` or `}` after the\n       \
+			 last statement\n --> <unknown>:1:10\n  |\n1 | let x = \
+			 1\n  |          ^ here\n"
+		);
+		assert_eq!(
+			diag.render_with(&source, &RenderOptions::default()),
+			diag.render(&source)
+		);
+	}
+
+	#[test]
+	fn wrap() {
+		assert_eq!(super::wrap("", 10), vec![""]);
+		assert_eq!(super::wrap("a  b\tc", 10), vec!["a b c"]);
+		assert_eq!(
+			super::wrap("a bb ccc dddd", 4),
+			vec!["a bb", "ccc", "dddd"]
+		);
+		assert_eq!(super::wrap("abcdefgh ij", 4), vec!["abcdefgh", "ij"]);
+	}
+
+	#[cfg(feature = "serde")]
+	#[test]
+	fn to_json() {
+		let source = Source::new(
+			Origin::Path("main.rs".into()),
+			"fn main() {\n\tlet x = 1;\n}\n".into(),
+		);
+		let diag = Diagnostic::new(Severity::Warning, "unused variable")
+			.with_label(Label::primary(Span::from(17_u32..18), "not used"));
+
+		assert_eq!(
+			diag.to_json(&source),
+			serde_json::json!({
+				"severity": "warning",
+				"code": null,
+				"message": "unused variable",
+				"labels": [{
+					"origin": "main.rs",
+					"start": { "line": 1, "col": 5 },
+					"end": { "line": 1, "col": 6 },
+					"message": "not used",
+				}],
+			})
+		);
+
+		let named = Source::from_str_named("stdin", source.data());
+		let json = diag.to_json(&named);
+		assert_eq!(json["labels"][0]["origin"], "stdin");
+
+		let unknown = Source::new(Origin::Unknown, source.data().into());
+		let json = diag.to_json(&unknown);
+		assert_eq!(json["labels"][0]["origin"], serde_json::Value::Null);
+	}
+
+	#[test]
+	fn sort_labels() {
+		let mut diag = Diagnostic::new(Severity::Error, "")
+			.with_label(Label::primary(Span::from(4_u32..6), "c"))
+			.with_label(Label::primary(Span::from(1_u32..3), "a"))
+			.with_label(Label::primary(Span::from(4_u32..5), "b"));
+		diag.sort_labels();
+
+		let messages: Vec<_> =
+			diag.labels.iter().map(|label| label.message.as_str()).collect();
+		assert_eq!(messages, vec!["a", "b", "c"]);
+	}
+
+	#[test]
+	fn dedup_labels() {
+		let mut diag = Diagnostic::new(Severity::Error, "")
+			.with_label(Label::primary(Span::from(4_u32..6), "a"))
+			.with_label(Label::primary(Span::from(1_u32..3), "b"))
+			.with_label(Label::primary(Span::from(4_u32..6), "a"))
+			.with_label(Label::secondary(Span::from(4_u32..6), "a"))
+			.with_label(Label::primary(Span::from(1_u32..3), "b"));
+		diag.dedup_labels();
+
+		assert_eq!(
+			diag.labels,
+			vec![
+				Label::primary(Span::from(4_u32..6), "a"),
+				Label::primary(Span::from(1_u32..3), "b"),
+				Label::secondary(Span::from(4_u32..6), "a"),
+			]
+		);
+	}
+
+	#[test]
+	fn render_dedup() {
+		let source = Source::new(Origin::Unknown, "let x;".into());
+		let label = Label::primary(Span::from(4_u32..5), "here");
+
+		let diag =
+			Diagnostic::new(Severity::Error, "oops").with_label(label.clone());
+		let duplicated = diag.clone().with_label(label);
+
+		assert_eq!(diag.render(&source), duplicated.render(&source));
+	}
+
+	#[test]
+	fn render_label_order() {
+		let source = Source::new(Origin::Unknown, "a = b;\nc = a;".into());
+		let first = Label::secondary(Span::from(0_u32..1), "defined here");
+		let second = Label::primary(Span::from(11_u32..12), "used here");
+
+		let diag = Diagnostic::new(Severity::Error, "moved value")
+			.with_label(first.clone())
+			.with_label(second.clone());
+		let reversed = Diagnostic::new(Severity::Error, "moved value")
+			.with_label(second)
+			.with_label(first);
+
+		assert_eq!(diag.render(&source), reversed.render(&source));
+	}
+
+	#[test]
+	fn render_trailing_whitespace() {
+		let source = Source::new(Origin::Unknown, "ab  \r\ncd".into());
+		let diag = Diagnostic::new(Severity::Warning, "trailing whitespace")
+			.with_label(Label::primary(Span::from(2_u32..4), "here"));
+
+		assert_eq!(
+			diag.render(&source),
+			"warning: trailing whitespace\n --> <unknown>:1:3\n  |\n1 | ab  \
+			 \n  |   ^^ here\n"
+		);
+
+		let diag = Diagnostic::error_at(Span::from(3_u32..5), "");
+		assert!(diag.render(&source).contains(" --> <unknown>:1:4\n"));
+		assert!(diag.render(&source).ends_with("1 | ab  \n  |    ^\n"));
+	}
+
+	#[test]
+	fn render_eof() {
+		let source = Source::new(Origin::Unknown, "let x\n".into());
+		let diag = Diagnostic::error_at(source.eof_span(), "unexpected end");
+
+		assert_eq!(
+			diag.render(&source),
+			"error: unexpected end\n --> <unknown>:1:6\n  |\n1 | let x\n  |      \
+			 ^\n"
+		);
+
+		let source = Source::new(Origin::Unknown, "let x".into());
+		let diag = Diagnostic::error_at(source.eof_span(), "unexpected end");
+
+		assert_eq!(
+			diag.render(&source),
+			"error: unexpected end\n --> <unknown>:1:6\n  |\n1 | let x\n  |      \
+			 ^\n"
+		);
+
+		let source = Source::new(Origin::Unknown, String::new());
+		let diag = Diagnostic::error_at(source.eof_span(), "unexpected end");
+
+		assert_eq!(
+			diag.render(&source),
+			"error: unexpected end\n --> <unknown>:1:1\n  |\n1 | \n  | ^\n"
+		);
+	}
+
+	#[test]
+	fn label_from_tuple() {
+		let errors = vec![
+			(Span::from(0_u32..2), String::from("expected `;`")),
+			(Span::from(6_u32..9), String::from("unknown identifier")),
+		];
+
+		let diag = errors.into_iter().fold(
+			Diagnostic::new(Severity::Error, "parsing failed"),
+			|d, e| d.with_label(e),
+		);
+
+		assert_eq!(
+			diag.labels,
+			vec![
+				Label::primary(Span::from(0_u32..2), "expected `;`"),
+				Label::primary(Span::from(6_u32..9), "unknown identifier"),
+			]
+		);
+	}
+}
@@ -10,7 +10,8 @@
 	// TODO: missing_docs,
 	// TODO: clippy::missing_docs_in_private_items,
 	missing_debug_implementations,
-	private_in_public,
+	private_interfaces,
+	private_bounds,
 	rust_2021_compatibility,
 	rust_2021_prelude_collisions,
 	rust_2021_incompatible_or_patterns,
@@ -30,6 +31,7 @@
 )]
 #![cfg_attr(docsrs, feature(doc_cfg), feature(doc_alias))]
 
+pub mod diag;
 pub mod loc;
 pub mod pos;
 pub mod span;
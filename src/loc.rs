@@ -1,5 +1,39 @@
 use std::fmt;
 
+/// A zero indexed line number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Line(pub usize);
+
+impl Line {
+	/// Returns this line as a [`usize`].
+	pub const fn as_usize(self) -> usize {
+		self.0
+	}
+}
+
+impl From<usize> for Line {
+	fn from(value: usize) -> Self {
+		Self(value)
+	}
+}
+
+/// A zero indexed column number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Column(pub usize);
+
+impl Column {
+	/// Returns this column as a [`usize`].
+	pub const fn as_usize(self) -> usize {
+		self.0
+	}
+}
+
+impl From<usize> for Column {
+	fn from(value: usize) -> Self {
+		Self(value)
+	}
+}
+
 /// A location inside a [`Source`](`crate::src::Source`).
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Loc {
@@ -11,9 +45,94 @@ pub struct Loc {
 }
 
 impl Loc {
-	pub fn new(line: usize, column: usize) -> Self {
+	pub const fn new(line: usize, column: usize) -> Self {
 		Self { line, column }
 	}
+
+	/// Creates a new location from a typed line and column.
+	///
+	/// Unlike [`Loc::new`], the arguments can not be swapped by accident:
+	///
+	/// ```compile_fail
+	/// use ptk::loc::{Column, Line, Loc};
+	///
+	/// let loc = Loc::from_line_col(Column(4), Line(2));
+	/// ```
+	pub const fn from_line_col(line: Line, column: Column) -> Self {
+		Self::new(line.as_usize(), column.as_usize())
+	}
+
+	/// Returns an iterator over the locations of the first column of each
+	/// line in `start_line..end_line`.
+	pub fn line_range(
+		start_line: usize,
+		end_line: usize,
+	) -> impl Iterator<Item = Self> {
+		(start_line..end_line).map(|line| Self::new(line, 0))
+	}
+
+	/// Moves the location past the given character.
+	///
+	/// The column is advanced by the byte width of the character to match the
+	/// locations returned by
+	/// [`Source::pos_to_loc`](`crate::src::Source::pos_to_loc`). A `\n`
+	/// moves the location to the start of the next line, so `\r\n` is also
+	/// handled as a single line break.
+	pub const fn advance(&mut self, c: char) {
+		if c == '\n' {
+			self.line += 1;
+			self.column = 0;
+		} else {
+			self.column += c.len_utf8();
+		}
+	}
+
+	/// Moves the location past all characters of the given string.
+	///
+	/// See [`Loc::advance`].
+	pub fn advance_str(&mut self, s: &str) {
+		s.chars().for_each(|c| self.advance(c));
+	}
+
+	/// Returns the signed `(line, column)` difference from this location to
+	/// `other`.
+	pub const fn delta(self, other: Self) -> (isize, isize) {
+		(
+			other.line as isize - self.line as isize,
+			other.column as isize - self.column as isize,
+		)
+	}
+
+	/// Converts this location to a zero indexed `(line, character)` pair as
+	/// used by the language server protocol.
+	///
+	/// The column is used as is, it is the responsibility of the caller to
+	/// make sure it is counted in UTF-16 code units. Values which do not fit
+	/// into a [`u32`] saturate at [`u32::MAX`].
+	pub const fn to_lsp_position(self) -> (u32, u32) {
+		(saturating_u32(self.line), saturating_u32(self.column))
+	}
+
+	/// Restricts the location to lie within `min..=max`.
+	///
+	/// Locations are compared line first and then by column, so a location
+	/// on a line between `min` and `max` is kept as is regardless of its
+	/// column.
+	///
+	/// # Panics
+	///
+	/// This function will panic if `min` is greater than `max`.
+	pub fn clamp_to(self, min: Self, max: Self) -> Self {
+		self.clamp(min, max)
+	}
+}
+
+const fn saturating_u32(value: usize) -> u32 {
+	if value > u32::MAX as usize {
+		u32::MAX
+	} else {
+		value as u32
+	}
 }
 
 impl fmt::Display for Loc {
@@ -21,3 +140,93 @@ impl fmt::Display for Loc {
 		write!(f, "{}:{}", self.line + 1, self.column + 1)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn from_line_col() {
+		let loc = Loc::from_line_col(Line(2), Column(4));
+		assert_eq!(loc, Loc::new(2, 4));
+
+		let loc = Loc::from_line_col(2.into(), 4.into());
+		assert_eq!(loc, Loc::new(2, 4));
+		assert_eq!(Line::from(2).as_usize(), 2);
+		assert_eq!(Column::from(4).as_usize(), 4);
+	}
+
+	#[test]
+	fn line_range() {
+		let locs: Vec<_> = Loc::line_range(2, 5).collect();
+		assert_eq!(locs, vec![Loc::new(2, 0), Loc::new(3, 0), Loc::new(4, 0)]);
+
+		assert_eq!(Loc::line_range(5, 5).count(), 0);
+	}
+
+	#[test]
+	fn advance_str() {
+		let mut loc = Loc::new(2, 4);
+		loc.advance_str("let x");
+		assert_eq!(loc, Loc::new(2, 9));
+
+		let mut loc = Loc::new(2, 4);
+		loc.advance_str("a\r\nbc\n\ndé");
+		assert_eq!(loc, Loc::new(5, 3));
+	}
+
+	#[test]
+	fn advance_str_matches_source() {
+		use crate::pos::Pos;
+		use crate::src::{Origin, Source};
+
+		const DATA: &str = "fn main() {\r\n\tlet é = 1;\n}";
+
+		let source = Source::new(Origin::Unknown, DATA.into());
+		let mut loc = Loc::new(0, 0);
+		loc.advance_str(DATA);
+
+		assert_eq!(Some(loc), source.pos_to_loc(Pos::from_usize(DATA.len())));
+	}
+
+	#[test]
+	fn delta() {
+		let lhs = Loc::new(3, 10);
+
+		assert_eq!(lhs.delta(Loc::new(5, 2)), (2, -8));
+		assert_eq!(lhs.delta(Loc::new(1, 12)), (-2, 2));
+		assert_eq!(lhs.delta(Loc::new(3, 4)), (0, -6));
+		assert_eq!(lhs.delta(lhs), (0, 0));
+	}
+
+	#[test]
+	fn clamp_to() {
+		let (min, max) = (Loc::new(2, 4), Loc::new(5, 1));
+
+		assert_eq!(Loc::new(1, 8).clamp_to(min, max), min);
+		assert_eq!(Loc::new(2, 2).clamp_to(min, max), min);
+		assert_eq!(Loc::new(5, 3).clamp_to(min, max), max);
+		assert_eq!(Loc::new(9, 0).clamp_to(min, max), max);
+		assert_eq!(Loc::new(3, 20).clamp_to(min, max), Loc::new(3, 20));
+		assert_eq!(Loc::new(2, 4).clamp_to(min, max), Loc::new(2, 4));
+	}
+
+	#[test]
+	#[should_panic]
+	fn clamp_to_inverted() {
+		let _ = Loc::new(3, 0).clamp_to(Loc::new(5, 1), Loc::new(2, 4));
+	}
+
+	#[test]
+	fn to_lsp_position() {
+		assert_eq!(Loc::new(11, 4).to_lsp_position(), (11, 4));
+		assert_eq!(Loc::new(0, 0).to_lsp_position(), (0, 0));
+	}
+
+	#[test]
+	#[cfg(target_pointer_width = "64")]
+	fn to_lsp_position_saturating() {
+		let loc = Loc::new(u32::MAX as usize + 1, 2);
+		assert_eq!(loc.to_lsp_position(), (u32::MAX, 2));
+	}
+}
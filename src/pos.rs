@@ -1,3 +1,8 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, Sub};
+
 pub type Width = u32;
 pub type IWidth = i32;
 
@@ -5,10 +10,21 @@ pub type IWidth = i32;
 ///
 /// This is mainly used to keep track of character or byte positions in a
 /// source file.
+///
+/// # Layout
+///
+/// This type is guaranteed to have the same layout and ABI as [`Width`]
+/// ([`u32`]), so it can be passed across FFI boundaries in place of one.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+#[repr(transparent)]
 pub struct Pos(pub Width);
 
 impl Pos {
+	/// The position zero, which is the start of any source.
+	pub const ZERO: Self = Self(0);
+
 	/// Creates a new position from a [`u32`].
 	pub const fn from_u32(value: u32) -> Self {
 		Self(value)
@@ -28,6 +44,90 @@ impl Pos {
 	pub const fn as_usize(self) -> usize {
 		self.0 as usize
 	}
+
+	/// Returns `true` if this is the position zero.
+	pub const fn is_zero(self) -> bool {
+		self.0 == 0
+	}
+
+	/// Returns the smaller of both positions.
+	#[must_use]
+	pub const fn min(self, other: Self) -> Self {
+		if self.0 <= other.0 {
+			self
+		} else {
+			other
+		}
+	}
+
+	/// Returns the greater of both positions.
+	#[must_use]
+	pub const fn max(self, other: Self) -> Self {
+		if self.0 >= other.0 {
+			self
+		} else {
+			other
+		}
+	}
+
+	/// Returns the position directly after this one.
+	///
+	/// # Panics
+	///
+	/// This function will panic if an overflow occures during the addition.
+	#[must_use]
+	pub const fn next(self) -> Self {
+		Self(
+			self.0
+				.checked_add(1)
+				.expect("Width overflow while adding to `Pos`"),
+		)
+	}
+
+	/// Returns the position directly before this one.
+	///
+	/// Returns `None` if this is the position zero.
+	pub const fn prev(self) -> Option<Self> {
+		match self.0.checked_sub(1) {
+			Some(value) => Some(Self(value)),
+			None => None,
+		}
+	}
+
+	/// Returns the signed distance from `other` to this position
+	/// (`self - other`).
+	///
+	/// Returns `None` if the distance does not fit into an [`IWidth`].
+	pub const fn checked_sub_pos(self, other: Self) -> Option<IWidth> {
+		let distance = signed_distance(self, other);
+
+		if distance < IWidth::MIN as i64 || distance > IWidth::MAX as i64 {
+			None
+		} else {
+			Some(distance as IWidth)
+		}
+	}
+
+	/// Returns the signed distance from `other` to this position
+	/// (`self - other`).
+	///
+	/// Distances which do not fit into an [`IWidth`] saturate at
+	/// [`IWidth::MIN`] or [`IWidth::MAX`].
+	pub const fn saturating_sub_pos(self, other: Self) -> IWidth {
+		let distance = signed_distance(self, other);
+
+		if distance < IWidth::MIN as i64 {
+			IWidth::MIN
+		} else if distance > IWidth::MAX as i64 {
+			IWidth::MAX
+		} else {
+			distance as IWidth
+		}
+	}
+}
+
+const fn signed_distance(lhs: Pos, rhs: Pos) -> i64 {
+	lhs.0 as i64 - rhs.0 as i64
 }
 
 impl From<u32> for Pos {
@@ -42,6 +142,102 @@ impl From<usize> for Pos {
 	}
 }
 
+impl fmt::LowerHex for Pos {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		fmt::LowerHex::fmt(&self.0, f)
+	}
+}
+
+impl fmt::UpperHex for Pos {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		fmt::UpperHex::fmt(&self.0, f)
+	}
+}
+
+impl PartialEq<u32> for Pos {
+	fn eq(&self, other: &u32) -> bool {
+		self.0 == *other
+	}
+}
+
+impl PartialEq<Pos> for u32 {
+	fn eq(&self, other: &Pos) -> bool {
+		*self == other.0
+	}
+}
+
+impl PartialOrd<u32> for Pos {
+	fn partial_cmp(&self, other: &u32) -> Option<Ordering> {
+		self.0.partial_cmp(other)
+	}
+}
+
+impl PartialOrd<Pos> for u32 {
+	fn partial_cmp(&self, other: &Pos) -> Option<Ordering> {
+		self.partial_cmp(&other.0)
+	}
+}
+
+#[cfg(feature = "arbitrary")]
+#[cfg_attr(docsrs, doc(cfg(feature = "arbitrary")))]
+impl<'a> arbitrary::Arbitrary<'a> for Pos {
+	fn arbitrary(
+		u: &mut arbitrary::Unstructured<'a>,
+	) -> arbitrary::Result<Self> {
+		Ok(Self::from_u32(u.arbitrary()?))
+	}
+
+	fn size_hint(depth: usize) -> (usize, Option<usize>) {
+		<Width as arbitrary::Arbitrary>::size_hint(depth)
+	}
+}
+
+impl Add<Width> for Pos {
+	type Output = Self;
+
+	/// Advances the position by the given amount.
+	///
+	/// # Panics
+	///
+	/// This function will panic if an overflow occures during the addition.
+	fn add(self, rhs: Width) -> Self::Output {
+		Self::from_u32(
+			self.as_u32()
+				.checked_add(rhs)
+				.expect("Width overflow while adding to `Pos`"),
+		)
+	}
+}
+
+impl Sub<Width> for Pos {
+	type Output = Self;
+
+	/// Moves the position back by the given amount.
+	///
+	/// # Panics
+	///
+	/// This function will panic if an underflow occures during the
+	/// subtraction.
+	fn sub(self, rhs: Width) -> Self::Output {
+		Self::from_u32(
+			self.as_u32()
+				.checked_sub(rhs)
+				.expect("Width underflow while subtracting from `Pos`"),
+		)
+	}
+}
+
+impl Sum for Pos {
+	/// Sums up all positions.
+	///
+	/// # Panics
+	///
+	/// This function will panic if an overflow occures during the addition.
+	fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+		iter.fold(Self::from_u32(0), |sum, pos| sum + pos.as_u32())
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -105,4 +301,157 @@ mod tests {
 		let pos = Pos::from_usize(value);
 		assert_eq!(pos.as_usize(), value);
 	}
+
+	#[test]
+	fn is_zero() {
+		assert!(Pos::from_u32(0).is_zero());
+		assert!(!Pos::from_u32(1).is_zero());
+	}
+
+	#[test]
+	fn min_max() {
+		const MAX: Pos = Pos::from_u32(3).max(Pos::from_u32(7));
+		const MIN: Pos = Pos::from_u32(3).min(Pos::from_u32(7));
+
+		assert_eq!(MAX, Pos::from_u32(7));
+		assert_eq!(MIN, Pos::from_u32(3));
+		assert_eq!(Pos::from_u32(7).max(Pos::from_u32(3)), Pos::from_u32(7));
+		assert_eq!(Pos::from_u32(7).min(Pos::from_u32(3)), Pos::from_u32(3));
+	}
+
+	#[test]
+	fn layout() {
+		use std::mem::{align_of, size_of};
+
+		const {
+			assert!(size_of::<Pos>() == size_of::<u32>());
+			assert!(align_of::<Pos>() == align_of::<u32>());
+		}
+	}
+
+	#[test]
+	fn hex() {
+		let pos = Pos::from_u32(0xbeef);
+		assert_eq!(format!("{:x}", pos), "beef");
+		assert_eq!(format!("{:#X}", pos), "0xBEEF");
+		assert_eq!(format!("{:06x}", pos), "00beef");
+	}
+
+	#[test]
+	fn eq_u32() {
+		assert_eq!(Pos::from_u32(10), 10);
+		assert_eq!(10, Pos::from_u32(10));
+		assert_ne!(Pos::from_u32(10), 11);
+		assert_ne!(11, Pos::from_u32(10));
+	}
+
+	#[test]
+	fn cmp_u32() {
+		let pos = Pos::from_u32(10);
+
+		assert!(pos < 11);
+		assert!(pos <= 10);
+		assert!(pos > 9);
+		assert!(9 < pos);
+		assert!(11 > pos);
+		assert!(10 >= pos);
+		assert_eq!(pos.partial_cmp(&10), Some(Ordering::Equal));
+	}
+
+	#[test]
+	fn add_width() {
+		let pos = Pos::from_u32(10) + 20;
+		assert_eq!(pos.as_u32(), 30);
+
+		let pos = Pos::from_u32(u32::MAX - 1) + 1;
+		assert_eq!(pos.as_u32(), u32::MAX);
+	}
+
+	#[test]
+	#[should_panic(expected = "Width overflow")]
+	fn add_width_overflow() {
+		let _ = Pos::from_u32(u32::MAX - 1) + 2;
+	}
+
+	#[test]
+	fn sub_width() {
+		let pos = Pos::from_u32(30) - 20;
+		assert_eq!(pos.as_u32(), 10);
+
+		let pos = Pos::from_u32(1) - 1;
+		assert_eq!(pos.as_u32(), 0);
+	}
+
+	#[test]
+	fn sum() {
+		let positions =
+			[Pos::from_u32(3), Pos::from_u32(10), Pos::from_u32(5)];
+
+		let sum: Pos = positions.iter().copied().sum();
+		assert_eq!(sum, Pos::from_u32(18));
+		assert_eq!(sum.as_usize() / positions.len(), 6);
+
+		let sum: Pos = std::iter::empty().sum();
+		assert_eq!(sum, Pos::from_u32(0));
+	}
+
+	#[test]
+	#[should_panic(expected = "Width overflow")]
+	fn sum_overflow() {
+		let _: Pos =
+			[Pos::from_u32(u32::MAX), Pos::from_u32(1)].into_iter().sum();
+	}
+
+	#[test]
+	#[should_panic(expected = "Width underflow")]
+	fn sub_width_underflow() {
+		let _ = Pos::from_u32(1) - 2;
+	}
+
+	#[test]
+	fn next_prev() {
+		assert_eq!(Pos::from_u32(4).next(), Pos::from_u32(5));
+		assert_eq!(Pos::ZERO.next(), Pos::from_u32(1));
+		assert_eq!(Pos::from_u32(4).prev(), Some(Pos::from_u32(3)));
+		assert_eq!(Pos::from_u32(1).prev(), Some(Pos::ZERO));
+		assert_eq!(Pos::ZERO.prev(), None);
+	}
+
+	#[test]
+	#[should_panic]
+	fn next_overflow() {
+		let _ = Pos::from_u32(u32::MAX).next();
+	}
+
+	#[test]
+	fn checked_sub_pos() {
+		assert_eq!(
+			Pos::from_u32(10).checked_sub_pos(Pos::from_u32(4)),
+			Some(6)
+		);
+		assert_eq!(
+			Pos::from_u32(4).checked_sub_pos(Pos::from_u32(10)),
+			Some(-6)
+		);
+		assert_eq!(Pos::from_u32(u32::MAX).checked_sub_pos(Pos::ZERO), None);
+		assert_eq!(Pos::ZERO.checked_sub_pos(Pos::from_u32(u32::MAX)), None);
+	}
+
+	#[test]
+	fn saturating_sub_pos() {
+		assert_eq!(Pos::from_u32(10).saturating_sub_pos(Pos::from_u32(4)), 6);
+		assert_eq!(Pos::from_u32(4).saturating_sub_pos(Pos::from_u32(10)), -6);
+		assert_eq!(
+			Pos::from_u32(u32::MAX).saturating_sub_pos(Pos::ZERO),
+			i32::MAX
+		);
+		assert_eq!(
+			Pos::ZERO.saturating_sub_pos(Pos::from_u32(u32::MAX)),
+			i32::MIN
+		);
+		assert_eq!(
+			Pos::from_u32(i32::MAX as u32).saturating_sub_pos(Pos::ZERO),
+			i32::MAX
+		);
+	}
 }
@@ -1,10 +1,52 @@
-use std::ops::{Index, Range};
+use std::fmt;
+use std::ops::{
+	Add, AddAssign, Bound, Index, Range, RangeBounds, RangeInclusive, Sub,
+	SubAssign,
+};
 
-use crate::pos::{IWidth, Pos};
+use crate::pos::{IWidth, Pos, Width};
+use crate::src::Source;
+
+/// Errors which can occure when creating a [`Span`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SpanError {
+	/// The given `low` was greater than the given `high`.
+	Inverted { low: Pos, high: Pos },
+
+	/// The given bound does not fit into a [`Pos`].
+	OutOfRange { bound: usize },
+}
+
+impl fmt::Display for SpanError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Inverted { low, high } => write!(
+				f,
+				"span low ({}) is greater than high ({})",
+				low.as_u32(),
+				high.as_u32()
+			),
+			Self::OutOfRange { bound } => write!(
+				f,
+				"span bound ({}) is greater than the maximum position ({})",
+				bound,
+				Width::MAX
+			),
+		}
+	}
+}
+
+impl std::error::Error for SpanError {}
 
 /// Represents a span with an inclusive start ([`Span::low`]) and an exclusive
 /// end ([`Span::high`]).
+///
+/// With the `serde` feature the span is (de)serialized as an object
+/// (`{"low": 1, "high": 4}`), see [`serde_array`] for a compact alternative.
+/// When deserializing, inverted bounds are switched like in [`Span::new`].
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "SpanObject"))]
 pub struct Span {
 	/// Inclusive start of the span.
 	pub low: Pos,
@@ -28,6 +70,206 @@ impl Span {
 		Self { low, high }
 	}
 
+	/// Creates a new span from the given ordered positions.
+	///
+	/// Unlike [`Span::new`] this can be used in constant expressions. The
+	/// caller must make sure that `low` is not greater than `high`, this is
+	/// only checked in debug builds.
+	///
+	/// ```
+	/// # use ptk::pos::Pos;
+	/// # use ptk::span::Span;
+	/// const HEADER: Span = Span::new_ordered(Pos::ZERO, Pos::from_u32(4));
+	///
+	/// assert_eq!(HEADER.len(), 4);
+	/// ```
+	#[must_use]
+	pub const fn new_ordered(low: Pos, high: Pos) -> Self {
+		debug_assert!(low.0 <= high.0, "span low is greater than high");
+
+		Self { low, high }
+	}
+
+	/// Creates a new span from the given positions.
+	///
+	/// Unlike [`Span::new`] the values are not switched if `low` is greater
+	/// than `high`.
+	///
+	/// # Errors
+	///
+	/// Returns [`SpanError::Inverted`] if `low` is greater than `high`.
+	pub fn try_new(low: Pos, high: Pos) -> Result<Self, SpanError> {
+		if low > high {
+			return Err(SpanError::Inverted { low, high });
+		}
+
+		Ok(Self { low, high })
+	}
+
+	/// Creates a new span from the given byte range.
+	///
+	/// Unlike the [`From`] conversion, the bounds are checked instead of
+	/// being truncated or switched.
+	///
+	/// # Errors
+	///
+	/// Returns [`SpanError::OutOfRange`] if a bound does not fit into a
+	/// [`Pos`] and [`SpanError::Inverted`] if `start` is greater than `end`.
+	pub fn try_from_range(range: Range<usize>) -> Result<Self, SpanError> {
+		let to_pos = |bound: usize| {
+			Width::try_from(bound)
+				.map(Pos::from_u32)
+				.map_err(|_| SpanError::OutOfRange { bound })
+		};
+
+		Self::try_new(to_pos(range.start)?, to_pos(range.end)?)
+	}
+
+	/// Creates a new empty span at the given position.
+	#[must_use]
+	pub const fn at(pos: Pos) -> Self {
+		Self { low: pos, high: pos }
+	}
+
+	/// Creates a new span starting at `start` which is `len` wide.
+	///
+	/// # Panics
+	///
+	/// This function will panic if an overflow occures while computing
+	/// [`Span::high`].
+	#[must_use]
+	pub fn from_start_len(start: Pos, len: Width) -> Self {
+		Self::new(start, start + len)
+	}
+
+	/// Returns the width of the span.
+	pub const fn len(self) -> Width {
+		self.high.as_u32() - self.low.as_u32()
+	}
+
+	/// Returns `true` if the span has a width of zero.
+	pub const fn is_empty(self) -> bool {
+		self.len() == 0
+	}
+
+	/// Returns `None` if the span is empty, otherwise the span itself.
+	pub const fn as_nonempty(self) -> Option<Self> {
+		if self.is_empty() {
+			None
+		} else {
+			Some(self)
+		}
+	}
+
+	/// Returns `true` if the position lies inside the span.
+	///
+	/// As [`Span::high`] is exclusive, it is not contained in the span (see
+	/// [`Span::contains_inclusive`]). An empty span contains no position.
+	pub fn contains(self, pos: Pos) -> bool {
+		self.low <= pos && pos < self.high
+	}
+
+	/// Returns `true` if the position lies inside the span, excluding
+	/// [`Span::high`].
+	///
+	/// This is the same as [`Span::contains`] and only exists to make the
+	/// distinction from [`Span::contains_inclusive`] explicit at the call
+	/// site.
+	pub fn contains_exclusive(self, pos: Pos) -> bool {
+		self.contains(pos)
+	}
+
+	/// Returns `true` if the position lies inside the span, including
+	/// [`Span::high`].
+	///
+	/// This is useful when the end of a span counts as part of it, e.g. for a
+	/// cursor placed directly after a token. An empty span contains its own
+	/// position.
+	pub fn contains_inclusive(self, pos: Pos) -> bool {
+		self.low <= pos && pos <= self.high
+	}
+
+	/// Returns the offset of the position from [`Span::low`] if the position
+	/// lies inside the span (see [`Span::contains`]).
+	pub fn relative_index(self, pos: Pos) -> Option<Width> {
+		self.contains(pos).then(|| pos.as_u32() - self.low.as_u32())
+	}
+
+	/// Splits the span at the start of the first line after [`Span::low`].
+	///
+	/// The first half keeps the line break. Returns `None` if the span does
+	/// not reach onto another line or is out of range for the source.
+	pub fn bisect(self, source: &Source) -> Option<(Self, Self)> {
+		let line = source.pos_to_loc(self.low)?.line;
+		let next_line_start = source.line_span(line + 1)?.low;
+
+		(next_line_start < self.high).then(|| {
+			(
+				Self::new(self.low, next_line_start),
+				Self::new(next_line_start, self.high),
+			)
+		})
+	}
+
+	/// Caps [`Span::high`] at the end of the line containing [`Span::low`].
+	///
+	/// The line break itself is not part of the line, so the resulting span
+	/// never crosses a line boundary. The span is returned as is if
+	/// [`Span::low`] is out of range for the source.
+	#[must_use]
+	pub fn shrink_to_fit_line(self, source: &Source) -> Self {
+		source
+			.pos_to_loc(self.low)
+			.and_then(|loc| source.line_span(loc.line))
+			.map_or(self, |line| self.clamp_high_to(line.high.max(self.low)))
+	}
+
+	/// Combines both spans like [`Span::union`] if the text between them
+	/// only consists of whitespace.
+	///
+	/// Overlapping or adjacent spans are always combined. Returns `None` if
+	/// the gap contains any other character or is not a valid span of the
+	/// source (see [`Source::get`]).
+	pub fn join_with_gap(self, other: Self, source: &Source) -> Option<Self> {
+		let (first, second) =
+			if self.low <= other.low { (self, other) } else { (other, self) };
+
+		if second.low <= first.high {
+			return Some(self.union(other));
+		}
+
+		source
+			.get(Self::new(first.high, second.low))?
+			.chars()
+			.all(char::is_whitespace)
+			.then(|| self.union(other))
+	}
+
+	/// Returns a value which formats the span together with the text it
+	/// covers in the given source, e.g. `10..15 "café"`.
+	///
+	/// The text is taken from [`Source::slice_lossy`], so the span may be out
+	/// of range or not lie on character boundaries.
+	pub fn debug_with(self, source: &Source) -> impl fmt::Debug + '_ {
+		DebugWith { span: self, source }
+	}
+
+	/// Returns the number of characters the span covers in the given source.
+	///
+	/// The span is clamped to the source. Only characters which start inside
+	/// the span are counted.
+	pub fn len_chars(&self, source: &Source) -> usize {
+		source.data().as_bytes()[source.clamp_to_source(*self)]
+			.iter()
+			.filter(|&&byte| !is_utf8_continuation(byte))
+			.count()
+	}
+
+	/// Returns `true` if the span is empty and starts at position zero.
+	pub const fn is_empty_at_origin(self) -> bool {
+		self.is_empty() && self.low.is_zero()
+	}
+
 	/// Replaces [`Span::low`] with the given value.
 	#[must_use]
 	pub fn with_low(self, low: Pos) -> Self {
@@ -42,6 +284,28 @@ impl Span {
 		Self::new(low, high)
 	}
 
+	/// Replaces [`Span::high`] so that the span is `len` wide.
+	///
+	/// # Panics
+	///
+	/// This function will panic if an overflow occures while computing
+	/// [`Span::high`].
+	#[must_use]
+	pub fn with_len(self, len: Width) -> Self {
+		Self::from_start_len(self.low, len)
+	}
+
+	/// Moves the span to start at `new_low` while keeping its width.
+	///
+	/// # Panics
+	///
+	/// This function will panic if an overflow occures while computing
+	/// [`Span::high`].
+	#[must_use]
+	pub fn shift_to(self, new_low: Pos) -> Self {
+		Self::from_start_len(new_low, self.len())
+	}
+
 	/// Shifts both [`Span::low`] and [`Span::high`] by the given amount.
 	///
 	/// # Panics
@@ -54,7 +318,7 @@ impl Span {
 		let (low, high) = (low.as_u32(), high.as_u32());
 
 		let amount_is_neg = amount.is_negative();
-		let abs_amount = amount.abs() as u32;
+		let abs_amount = amount.unsigned_abs();
 
 		let (low, high) = if amount_is_neg {
 			(
@@ -82,7 +346,7 @@ impl Span {
 		let (low, high) = (low.as_u32(), high.as_u32());
 
 		let amount_is_neg = amount.is_negative();
-		let abs_amount = amount.abs() as u32;
+		let abs_amount = amount.unsigned_abs();
 
 		let (low, high) = if amount_is_neg {
 			(low - abs_amount, high - abs_amount)
@@ -105,7 +369,7 @@ impl Span {
 		let low = low.as_u32();
 
 		let amount_is_neg = amount.is_negative();
-		let abs_amount = amount.abs() as u32;
+		let abs_amount = amount.unsigned_abs();
 
 		let low = if amount_is_neg {
 			low.checked_sub(abs_amount)
@@ -125,7 +389,7 @@ impl Span {
 		let low = low.as_u32();
 
 		let amount_is_neg = amount.is_negative();
-		let abs_amount = amount.abs() as u32;
+		let abs_amount = amount.unsigned_abs();
 
 		let low =
 			if amount_is_neg { low - abs_amount } else { low + abs_amount };
@@ -145,7 +409,7 @@ impl Span {
 		let high = high.as_u32();
 
 		let amount_is_neg = amount.is_negative();
-		let abs_amount = amount.abs() as u32;
+		let abs_amount = amount.unsigned_abs();
 
 		let high = if amount_is_neg {
 			high.checked_sub(abs_amount)
@@ -165,7 +429,7 @@ impl Span {
 		let high = high.as_u32();
 
 		let amount_is_neg = amount.is_negative();
-		let abs_amount = amount.abs() as u32;
+		let abs_amount = amount.unsigned_abs();
 
 		let high =
 			if amount_is_neg { high - abs_amount } else { high + abs_amount };
@@ -173,6 +437,95 @@ impl Span {
 		Self::new(low, Pos::from_u32(high))
 	}
 
+	/// Extends the span to the front by moving [`Span::low`] back by the given
+	/// amount.
+	///
+	/// [`Span::low`] saturates at zero.
+	#[must_use]
+	pub fn pad_start(self, amount: Width) -> Self {
+		let Self { low, high } = self;
+		let low = low.as_u32().saturating_sub(amount);
+
+		Self::new(Pos::from_u32(low), high)
+	}
+
+	/// Extends the span to the back by moving [`Span::high`] forward by the
+	/// given amount.
+	///
+	/// # Panics
+	///
+	/// This function will panic if an overflow occures during the padding.
+	#[must_use]
+	pub fn pad_end(self, amount: Width) -> Self {
+		let Self { low, high } = self;
+		Self::new(low, high + amount)
+	}
+
+	/// Caps [`Span::high`] at `max`.
+	///
+	/// [`Span::low`] is capped as well, so that the span stays ordered.
+	#[must_use]
+	pub const fn clamp_high_to(self, max: Pos) -> Self {
+		Self { low: self.low.min(max), high: self.high.min(max) }
+	}
+
+	/// Raises [`Span::low`] to at least `min`.
+	///
+	/// [`Span::high`] is raised as well, so that the span stays ordered.
+	#[must_use]
+	pub const fn clamp_low_to(self, min: Pos) -> Self {
+		Self { low: self.low.max(min), high: self.high.max(min) }
+	}
+
+	/// Limits the span to at most `max` bytes, keeping [`Span::low`].
+	#[must_use]
+	pub const fn clamp_len(self, max: Width) -> Self {
+		if self.len() > max {
+			// Can not overflow as `low + max` is smaller than `high`.
+			Self { low: self.low, high: Pos::from_u32(self.low.0 + max) }
+		} else {
+			self
+		}
+	}
+
+	/// Translates a span relative to `base` into an absolute span by
+	/// shifting both bounds by `base`.
+	///
+	/// # Panics
+	///
+	/// This function will panic if an overflow occures during the shifting.
+	#[must_use]
+	pub fn absolute_from(self, base: Pos) -> Self {
+		let Self { low, high } = self;
+		Self::new(low + base.as_u32(), high + base.as_u32())
+	}
+
+	/// Applies the function to both [`Span::low`] and [`Span::high`].
+	///
+	/// # Note
+	///
+	/// Like with [`Span::new`], if the mapped `low` is greater than the
+	/// mapped `high` the values will be switched.
+	#[must_use]
+	pub fn map_pos(self, f: impl Fn(Pos) -> Pos) -> Self {
+		Self::new(f(self.low), f(self.high))
+	}
+
+	/// Translates the span into a span relative to the start of `parent`.
+	///
+	/// This is the inverse of [`Span::absolute_from`]. Returns `None` if the
+	/// span does not lie inside `parent`.
+	pub fn relative_span(self, parent: Self) -> Option<Self> {
+		if parent.low <= self.low && self.high <= parent.high {
+			Some(Self {
+				low: Pos::from_u32(self.low.0 - parent.low.0),
+				high: Pos::from_u32(self.high.0 - parent.low.0),
+			})
+		} else {
+			None
+		}
+	}
+
 	/// Combines two spans and creates a new span which encloses both.
 	#[must_use]
 	pub fn union(self, other: Self) -> Self {
@@ -182,6 +535,39 @@ impl Span {
 		Self::new(Pos::from_u32(low), Pos::from_u32(high))
 	}
 
+	/// Returns the span which is covered by both spans.
+	///
+	/// Returns `None` if the spans do not overlap. As [`Span::high`] is
+	/// exclusive, adjacent spans do not overlap.
+	#[must_use]
+	pub fn intersection(self, other: Self) -> Option<Self> {
+		let low = std::cmp::max(self.low, other.low);
+		let high = std::cmp::min(self.high, other.high);
+
+		(low < high).then(|| Self::new(low, high))
+	}
+
+	/// Returns the span which is covered by both spans, including a shared
+	/// boundary.
+	///
+	/// Unlike [`Span::intersection`], adjacent spans (one ends where the other
+	/// starts) result in an empty span at the shared boundary. Returns `None`
+	/// if the spans are neither overlapping nor adjacent.
+	#[must_use]
+	pub fn touching_intersection(self, other: Self) -> Option<Self> {
+		let low = std::cmp::max(self.low, other.low);
+		let high = std::cmp::min(self.high, other.high);
+
+		(low <= high).then(|| Self::new(low, high))
+	}
+
+	/// Returns the width of the span which is covered by both spans.
+	///
+	/// Returns `0` if the spans do not overlap (see [`Span::intersection`]).
+	pub fn overlap_len(self, other: Self) -> Width {
+		self.intersection(other).map_or(0, Self::len)
+	}
+
 	/// Converts this span to a range.
 	pub const fn to_pos_range(self) -> Range<Pos> {
 		self.low..self.high
@@ -196,6 +582,106 @@ impl Span {
 	pub const fn to_usize_range(self) -> Range<usize> {
 		self.low.as_usize()..self.high.as_usize()
 	}
+
+	/// Converts this span to an inclusive range ending at the last position
+	/// inside the span.
+	///
+	/// # Panics
+	///
+	/// This function will panic if the span is empty, as an inclusive range
+	/// can not represent it.
+	pub const fn to_inclusive_pos_range(self) -> RangeInclusive<Pos> {
+		self.low..=Pos::from_u32(self.last_u32())
+	}
+
+	/// Converts this span to an inclusive range ending at the last position
+	/// inside the span.
+	///
+	/// # Panics
+	///
+	/// This function will panic if the span is empty, as an inclusive range
+	/// can not represent it.
+	pub const fn to_inclusive_u32_range(self) -> RangeInclusive<u32> {
+		self.low.as_u32()..=self.last_u32()
+	}
+
+	/// Converts this span to an inclusive range ending at the last position
+	/// inside the span.
+	///
+	/// # Panics
+	///
+	/// This function will panic if the span is empty, as an inclusive range
+	/// can not represent it.
+	pub const fn to_inclusive_usize_range(self) -> RangeInclusive<usize> {
+		self.low.as_usize()..=self.last_u32() as usize
+	}
+
+	/// Returns the last position inside the span.
+	const fn last_u32(self) -> u32 {
+		assert!(!self.is_empty(), "Empty span has no last position");
+		self.high.as_u32() - 1
+	}
+
+	/// Converts this span to a `(low, high)` pair.
+	pub const fn as_pos_pair(self) -> (Pos, Pos) {
+		(self.low, self.high)
+	}
+
+	/// Converts this span to a `(low, high)` pair.
+	pub const fn as_u32_pair(self) -> (u32, u32) {
+		(self.low.as_u32(), self.high.as_u32())
+	}
+
+	/// Converts this span to a `(low, high)` pair.
+	pub const fn as_usize_pair(self) -> (usize, usize) {
+		(self.low.as_usize(), self.high.as_usize())
+	}
+
+	/// Packs this span into a single [`u64`] with [`Span::low`] in the upper
+	/// and [`Span::high`] in the lower 32 bits.
+	pub const fn to_packed_u64(self) -> u64 {
+		(self.low.as_u32() as u64) << 32 | self.high.as_u32() as u64
+	}
+
+	/// Unpacks a span packed with [`Span::to_packed_u64`].
+	///
+	/// # Note
+	///
+	/// Like with [`Span::new`], if `low` is greater than `high` the values
+	/// will be switched.
+	#[must_use]
+	pub const fn from_packed_u64(packed: u64) -> Self {
+		let low = Pos::from_u32((packed >> 32) as u32);
+		let high = Pos::from_u32(packed as u32);
+
+		if low.0 > high.0 {
+			Self { low: high, high: low }
+		} else {
+			Self { low, high }
+		}
+	}
+}
+
+const fn is_utf8_continuation(byte: u8) -> bool {
+	byte & 0b1100_0000 == 0b1000_0000
+}
+
+/// Formats a span together with its text (see [`Span::debug_with`]).
+struct DebugWith<'a> {
+	span: Span,
+	source: &'a Source,
+}
+
+impl fmt::Debug for DebugWith<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"{}..{} {:?}",
+			self.span.low.as_u32(),
+			self.span.high.as_u32(),
+			self.source.slice_lossy(self.span)
+		)
+	}
 }
 
 impl<P> From<Range<P>> for Span
@@ -207,6 +693,132 @@ where
 	}
 }
 
+#[cfg(feature = "arbitrary")]
+#[cfg_attr(docsrs, doc(cfg(feature = "arbitrary")))]
+impl<'a> arbitrary::Arbitrary<'a> for Span {
+	/// Generates an arbitrary span which is always correctly ordered.
+	fn arbitrary(
+		u: &mut arbitrary::Unstructured<'a>,
+	) -> arbitrary::Result<Self> {
+		Ok(Self::new(u.arbitrary()?, u.arbitrary()?))
+	}
+
+	fn size_hint(depth: usize) -> (usize, Option<usize>) {
+		<(Pos, Pos) as arbitrary::Arbitrary>::size_hint(depth)
+	}
+}
+
+impl RangeBounds<Pos> for Span {
+	fn start_bound(&self) -> Bound<&Pos> {
+		Bound::Included(&self.low)
+	}
+
+	fn end_bound(&self) -> Bound<&Pos> {
+		Bound::Excluded(&self.high)
+	}
+}
+
+impl Add<IWidth> for Span {
+	type Output = Self;
+
+	/// Shifts the span by the given amount (see [`Span::shift_by`]).
+	fn add(self, rhs: IWidth) -> Self::Output {
+		self.shift_by(rhs)
+	}
+}
+
+impl AddAssign<IWidth> for Span {
+	/// Shifts the span by the given amount (see [`Span::shift_by`]).
+	fn add_assign(&mut self, rhs: IWidth) {
+		*self = *self + rhs;
+	}
+}
+
+impl Sub<IWidth> for Span {
+	type Output = Self;
+
+	/// Shifts the span by the negated amount (see [`Span::shift_by`]).
+	fn sub(self, rhs: IWidth) -> Self::Output {
+		// Negating `rhs` itself would overflow for `IWidth::MIN`.
+		let amount = rhs.unsigned_abs();
+
+		if rhs.is_negative() {
+			Self::new(self.low + amount, self.high + amount)
+		} else {
+			Self::new(self.low - amount, self.high - amount)
+		}
+	}
+}
+
+impl SubAssign<IWidth> for Span {
+	/// Shifts the span by the negated amount (see [`Span::shift_by`]).
+	fn sub_assign(&mut self, rhs: IWidth) {
+		*self = *self - rhs;
+	}
+}
+
+impl fmt::LowerHex for Span {
+	/// Formats the span as `low..high`.
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		fmt::LowerHex::fmt(&self.low, f)?;
+		f.write_str("..")?;
+		fmt::LowerHex::fmt(&self.high, f)
+	}
+}
+
+impl fmt::UpperHex for Span {
+	/// Formats the span as `low..high`.
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		fmt::UpperHex::fmt(&self.low, f)?;
+		f.write_str("..")?;
+		fmt::UpperHex::fmt(&self.high, f)
+	}
+}
+
+/// Object form of a [`Span`] which is deserialized through [`Span::new`].
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct SpanObject {
+	low: Pos,
+	high: Pos,
+}
+
+#[cfg(feature = "serde")]
+impl From<SpanObject> for Span {
+	fn from(object: SpanObject) -> Self {
+		Self::new(object.low, object.high)
+	}
+}
+
+/// (De)serializes a [`Span`] as a two element array `[low, high]`.
+///
+/// Use it with `#[serde(with = "ptk::span::serde_array")]`. When
+/// deserializing, inverted bounds are switched like in [`Span::new`].
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+pub mod serde_array {
+	use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+	use super::Span;
+	use crate::pos::Pos;
+
+	#[allow(clippy::trivially_copy_pass_by_ref)]
+	pub fn serialize<S>(span: &Span, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		[span.low, span.high].serialize(serializer)
+	}
+
+	pub fn deserialize<'de, D>(deserializer: D) -> Result<Span, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		let [low, high] = <[Pos; 2]>::deserialize(deserializer)?;
+		Ok(Span::new(low, high))
+	}
+}
+
 impl Index<Span> for str {
 	type Output = Self;
 
@@ -241,6 +853,260 @@ mod tests {
 		assert_eq!(span.high.as_u32(), 0xdead);
 	}
 
+	#[test]
+	fn new_ordered() {
+		const HEADER: Span = Span::new_ordered(Pos::ZERO, Pos::from_u32(4));
+
+		assert_eq!(HEADER, Span::new(Pos::ZERO, Pos::from_u32(4)));
+		assert_eq!(
+			Span::new_ordered(Pos::from_u32(3), Pos::from_u32(3)),
+			Span::at(Pos::from_u32(3))
+		);
+	}
+
+	#[test]
+	#[cfg(debug_assertions)]
+	#[should_panic]
+	fn new_ordered_inverted() {
+		let _ = Span::new_ordered(Pos::from_u32(4), Pos::ZERO);
+	}
+
+	#[test]
+	fn try_new() {
+		let span = Span::try_new(Pos::from_u32(10), Pos::from_u32(100));
+		assert_eq!(span, Ok(Span::from(10_u32..100)));
+
+		let span = Span::try_new(Pos::from_u32(10), Pos::from_u32(10));
+		assert_eq!(span, Ok(Span::from(10_u32..10)));
+	}
+
+	#[test]
+	fn try_new_inverted() {
+		let span = Span::try_new(Pos::from_u32(0xdead), Pos::from_u32(0xbeef));
+		assert_eq!(
+			span,
+			Err(SpanError::Inverted {
+				low: Pos::from_u32(0xdead),
+				high: Pos::from_u32(0xbeef)
+			})
+		);
+	}
+
+	#[test]
+	#[allow(clippy::reversed_empty_ranges)]
+	fn try_from_range() {
+		assert_eq!(Span::try_from_range(2..5), Ok(Span::from(2_u32..5)));
+		assert_eq!(Span::try_from_range(5..5), Ok(Span::from(5_u32..5)));
+		assert_eq!(
+			Span::try_from_range(5..2),
+			Err(SpanError::Inverted {
+				low: Pos::from_u32(5),
+				high: Pos::from_u32(2)
+			})
+		);
+
+		let too_large = u32::MAX as usize + 1;
+		assert_eq!(
+			Span::try_from_range(0..too_large),
+			Err(SpanError::OutOfRange { bound: too_large })
+		);
+		assert_eq!(
+			Span::try_from_range(too_large..0),
+			Err(SpanError::OutOfRange { bound: too_large })
+		);
+	}
+
+	#[test]
+	fn at() {
+		let span = Span::at(Pos::from_u32(10));
+		assert_eq!(span.low.as_u32(), 10);
+		assert_eq!(span.high.as_u32(), 10);
+	}
+
+	#[test]
+	fn len() {
+		let span = Span::new(Pos::from_u32(10), Pos::from_u32(14));
+		assert_eq!(span.len(), 4);
+		assert!(!span.is_empty());
+
+		let span = Span::at(Pos::from_u32(10));
+		assert_eq!(span.len(), 0);
+		assert!(span.is_empty());
+	}
+
+	#[test]
+	fn as_nonempty() {
+		assert_eq!(Span::at(Pos::from_u32(3)).as_nonempty(), None);
+		assert_eq!(
+			Span::from(3_u32..5).as_nonempty(),
+			Some(Span::from(3_u32..5))
+		);
+	}
+
+	#[test]
+	fn is_empty_at_origin() {
+		assert!(Span::at(Pos::from_u32(0)).is_empty_at_origin());
+		assert!(!Span::at(Pos::from_u32(1)).is_empty_at_origin());
+		assert!(!Span::from(0_u32..1).is_empty_at_origin());
+	}
+
+	#[test]
+	fn contains() {
+		let span = Span::new(Pos::from_u32(10), Pos::from_u32(14));
+		assert!(!span.contains(Pos::from_u32(9)));
+		assert!(span.contains(Pos::from_u32(10)));
+		assert!(span.contains(Pos::from_u32(13)));
+		assert!(!span.contains(Pos::from_u32(14)));
+		assert!(!Span::at(Pos::from_u32(10)).contains(Pos::from_u32(10)));
+	}
+
+	#[test]
+	fn contains_exclusive_inclusive() {
+		let span = Span::from(2_u32..5);
+
+		assert!(span.contains_exclusive(Pos::from_u32(2)));
+		assert!(span.contains_inclusive(Pos::from_u32(2)));
+		assert!(!span.contains_exclusive(Pos::from_u32(5)));
+		assert!(span.contains_inclusive(Pos::from_u32(5)));
+		assert!(!span.contains_inclusive(Pos::from_u32(6)));
+		assert!(!span.contains_inclusive(Pos::from_u32(1)));
+
+		let empty = Span::at(Pos::from_u32(3));
+		assert!(!empty.contains_exclusive(Pos::from_u32(3)));
+		assert!(empty.contains_inclusive(Pos::from_u32(3)));
+	}
+
+	#[test]
+	fn relative_index() {
+		let span = Span::new(Pos::from_u32(10), Pos::from_u32(14));
+		assert_eq!(span.relative_index(Pos::from_u32(10)), Some(0));
+		assert_eq!(span.relative_index(Pos::from_u32(12)), Some(2));
+		assert_eq!(span.relative_index(Pos::from_u32(14)), None);
+		assert_eq!(span.relative_index(Pos::from_u32(3)), None);
+		assert_eq!(span.relative_index(Pos::from_u32(20)), None);
+	}
+
+	#[test]
+	fn bisect() {
+		let source =
+			Source::new(crate::src::Origin::Unknown, "Hello\nWorld\n".into());
+
+		let span = Span::new(Pos::from_u32(3), Pos::from_u32(9));
+		assert_eq!(
+			span.bisect(&source),
+			Some((Span::from(3_u32..6), Span::from(6_u32..9)))
+		);
+
+		let span = Span::new(Pos::from_u32(3), Pos::from_u32(6));
+		assert_eq!(span.bisect(&source), None);
+
+		let span = Span::new(Pos::from_u32(7), Pos::from_u32(12));
+		assert_eq!(span.bisect(&source), None);
+	}
+
+	#[test]
+	fn shrink_to_fit_line() {
+		let source = Source::new(
+			crate::src::Origin::Unknown,
+			"let x;\nlet y;\n".into(),
+		);
+
+		// Spills onto the next line.
+		assert_eq!(
+			Span::from(4_u32..9).shrink_to_fit_line(&source),
+			Span::from(4_u32..6)
+		);
+		// Only includes the line break.
+		assert_eq!(
+			Span::from(4_u32..7).shrink_to_fit_line(&source),
+			Span::from(4_u32..6)
+		);
+		// Already within a line.
+		assert_eq!(
+			Span::from(7_u32..10).shrink_to_fit_line(&source),
+			Span::from(7_u32..10)
+		);
+		// Starts on the line break.
+		assert_eq!(
+			Span::from(6_u32..9).shrink_to_fit_line(&source),
+			Span::from(6_u32..6)
+		);
+		assert_eq!(
+			Span::from(20_u32..30).shrink_to_fit_line(&source),
+			Span::from(20_u32..30)
+		);
+	}
+
+	#[test]
+	fn join_with_gap() {
+		let source = Source::new(
+			crate::src::Origin::Unknown,
+			"foo  \t bar, baz\nqux".into(),
+		);
+		let (foo, bar) = (Span::from(0_u32..3), Span::from(7_u32..10));
+		let (baz, qux) = (Span::from(12_u32..15), Span::from(16_u32..19));
+
+		assert_eq!(
+			foo.join_with_gap(bar, &source),
+			Some(Span::from(0_u32..10))
+		);
+		assert_eq!(
+			bar.join_with_gap(foo, &source),
+			Some(Span::from(0_u32..10))
+		);
+		assert_eq!(
+			baz.join_with_gap(qux, &source),
+			Some(Span::from(12_u32..19))
+		);
+		assert_eq!(bar.join_with_gap(baz, &source), None);
+		assert_eq!(foo.join_with_gap(baz, &source), None);
+
+		// Overlapping and adjacent spans.
+		assert_eq!(
+			foo.join_with_gap(Span::from(2_u32..5), &source),
+			Some(Span::from(0_u32..5))
+		);
+		assert_eq!(
+			foo.join_with_gap(Span::from(3_u32..4), &source),
+			Some(Span::from(0_u32..4))
+		);
+		assert_eq!(qux.join_with_gap(Span::from(30_u32..31), &source), None);
+	}
+
+	#[test]
+	fn debug_with() {
+		let source = Source::new(
+			crate::src::Origin::Unknown,
+			"let name = café;".into(),
+		);
+
+		assert_eq!(
+			format!("{:?}", Span::from(11_u32..16).debug_with(&source)),
+			r#"11..16 "café""#
+		);
+		assert_eq!(
+			format!("{:?}", Span::from(3_u32..4).debug_with(&source)),
+			r#"3..4 " ""#
+		);
+		assert_eq!(
+			format!("{:?}", Span::from(16_u32..20).debug_with(&source)),
+			r#"16..20 ";""#
+		);
+	}
+
+	#[test]
+	fn len_chars() {
+		let source =
+			Source::new(crate::src::Origin::Unknown, "naïve café".into());
+
+		let span = Span::new(Pos::from_u32(0), Pos::from_u32(12));
+		assert_eq!(span.len(), 12);
+		assert_eq!(span.len_chars(&source), 10);
+
+		let span = Span::new(Pos::from_u32(3), Pos::from_u32(100));
+		assert_eq!(span.len_chars(&source), 7);
+	}
+
 	#[test]
 	fn with_low() {
 		let span = Span::new(Pos::from_u32(20), Pos::from_u32(50));
@@ -267,6 +1133,57 @@ mod tests {
 		assert_eq!(span.high.as_u32(), 20);
 	}
 
+	#[test]
+	fn with_len() {
+		let span = Span::new(Pos::from_u32(20), Pos::from_u32(50));
+		let span = span.with_len(5);
+		assert_eq!(span.low.as_u32(), 20);
+		assert_eq!(span.high.as_u32(), 25);
+
+		let span = span.with_len(0);
+		assert_eq!(span.low.as_u32(), 20);
+		assert_eq!(span.high.as_u32(), 20);
+	}
+
+	#[test]
+	#[should_panic]
+	fn with_len_overflow() {
+		let span =
+			Span::new(Pos::from_u32(u32::MAX - 1), Pos::from_u32(u32::MAX));
+		let _ = span.with_len(2);
+	}
+
+	#[test]
+	fn from_start_len() {
+		let span = Span::from_start_len(Pos::from_u32(10), 4);
+		assert_eq!(span.low.as_u32(), 10);
+		assert_eq!(span.high.as_u32(), 14);
+
+		let span = Span::from_start_len(Pos::from_u32(10), 0);
+		assert_eq!(span.low.as_u32(), 10);
+		assert_eq!(span.high.as_u32(), 10);
+	}
+
+	#[test]
+	fn shift_to() {
+		let span = Span::new(Pos::from_u32(20), Pos::from_u32(25));
+
+		let shifted = span.shift_to(Pos::from_u32(100));
+		assert_eq!(shifted.low.as_u32(), 100);
+		assert_eq!(shifted.high.as_u32(), 105);
+
+		let shifted = span.shift_to(Pos::from_u32(3));
+		assert_eq!(shifted.low.as_u32(), 3);
+		assert_eq!(shifted.high.as_u32(), 8);
+	}
+
+	#[test]
+	#[should_panic]
+	fn shift_to_overflow() {
+		let span = Span::new(Pos::from_u32(20), Pos::from_u32(25));
+		let _ = span.shift_to(Pos::from_u32(u32::MAX - 2));
+	}
+
 	#[test]
 	fn shift_by_pos() {
 		let span = Span::new(Pos::from_u32(0), Pos::from_u32(100));
@@ -283,6 +1200,44 @@ mod tests {
 		assert_eq!(span.high.as_u32(), 80);
 	}
 
+	#[test]
+	fn range_bounds() {
+		use std::collections::BTreeMap;
+
+		let map: BTreeMap<_, _> =
+			(0..10).map(|pos| (Pos::from_u32(pos * 2), pos)).collect();
+		let span = Span::new(Pos::from_u32(4), Pos::from_u32(10));
+
+		let values: Vec<_> =
+			map.range(span).map(|(_, &value)| value).collect();
+		assert_eq!(values, vec![2, 3, 4]);
+	}
+
+	#[test]
+	fn add_sub() {
+		let span = Span::new(Pos::from_u32(21), Pos::from_u32(100));
+		assert_eq!(span + 20, span.shift_by(20));
+		assert_eq!(span + -20, span.shift_by(-20));
+		assert_eq!(span - 20, span.shift_by(-20));
+		assert_eq!(span - -20, span.shift_by(20));
+
+		let mut shifted = span;
+		shifted += 5;
+		assert_eq!(shifted, span.shift_by(5));
+		shifted -= 10;
+		assert_eq!(shifted, span.shift_by(-5));
+
+		let span = Span::new(Pos::from_u32(0), Pos::from_u32(1));
+		assert_eq!(span - IWidth::MIN, span.shift_by(IWidth::MAX).shift_by(1));
+	}
+
+	#[test]
+	#[should_panic]
+	fn sub_underflow() {
+		let span = Span::new(Pos::from_u32(1), Pos::from_u32(100));
+		let _ = span - 2;
+	}
+
 	#[test]
 	fn shift_low_by_pos() {
 		let span = Span::new(Pos::from_u32(0), Pos::from_u32(100));
@@ -315,6 +1270,174 @@ mod tests {
 		assert_eq!(span.high.as_u32(), 80);
 	}
 
+	#[test]
+	fn pad_start() {
+		let span = Span::new(Pos::from_u32(20), Pos::from_u32(50));
+		let span = span.pad_start(5);
+		assert_eq!(span.low.as_u32(), 15);
+		assert_eq!(span.high.as_u32(), 50);
+
+		let span = span.pad_start(100);
+		assert_eq!(span.low.as_u32(), 0);
+		assert_eq!(span.high.as_u32(), 50);
+	}
+
+	#[test]
+	fn pad_end() {
+		let span = Span::new(Pos::from_u32(20), Pos::from_u32(50));
+		let span = span.pad_end(5);
+		assert_eq!(span.low.as_u32(), 20);
+		assert_eq!(span.high.as_u32(), 55);
+	}
+
+	#[test]
+	#[should_panic]
+	fn pad_end_overflow() {
+		let span = Span::new(Pos::from_u32(20), Pos::from_u32(u32::MAX));
+		let _ = span.pad_end(1);
+	}
+
+	#[test]
+	fn clamp_high_to() {
+		let span = Span::new(Pos::from_u32(10), Pos::from_u32(20));
+		assert_eq!(
+			span.clamp_high_to(Pos::from_u32(15)),
+			Span::from(10_u32..15)
+		);
+		assert_eq!(span.clamp_high_to(Pos::from_u32(30)), span);
+		assert_eq!(span.clamp_high_to(Pos::from_u32(5)), Span::from(5_u32..5));
+	}
+
+	#[test]
+	fn clamp_low_to() {
+		let span = Span::new(Pos::from_u32(10), Pos::from_u32(20));
+		assert_eq!(
+			span.clamp_low_to(Pos::from_u32(15)),
+			Span::from(15_u32..20)
+		);
+		assert_eq!(span.clamp_low_to(Pos::from_u32(5)), span);
+		assert_eq!(
+			span.clamp_low_to(Pos::from_u32(25)),
+			Span::from(25_u32..25)
+		);
+	}
+
+	#[test]
+	fn clamp_len() {
+		let span = Span::new(Pos::from_u32(10), Pos::from_u32(14));
+		assert_eq!(span.clamp_len(4), span);
+		assert_eq!(span.clamp_len(10), span);
+		assert_eq!(span.clamp_len(2), Span::from(10_u32..12));
+		assert_eq!(span.clamp_len(0), Span::from(10_u32..10));
+	}
+
+	#[test]
+	fn absolute_from() {
+		let span = Span::new(Pos::from_u32(2), Pos::from_u32(5));
+		let span = span.absolute_from(Pos::from_u32(10));
+		assert_eq!(span.low.as_u32(), 12);
+		assert_eq!(span.high.as_u32(), 15);
+	}
+
+	#[test]
+	fn map_pos() {
+		let span = Span::from(3_u32..5);
+
+		assert_eq!(
+			span.map_pos(|pos| Pos::from_u32(pos.as_u32() * 2)),
+			Span::from(6_u32..10)
+		);
+		assert_eq!(
+			span.map_pos(|pos| Pos::from_u32(10 - pos.as_u32())),
+			Span::from(5_u32..7)
+		);
+	}
+
+	#[test]
+	fn relative_span() {
+		let parent = Span::from(10_u32..20);
+
+		assert_eq!(
+			Span::from(12_u32..15).relative_span(parent),
+			Some(Span::from(2_u32..5))
+		);
+		assert_eq!(parent.relative_span(parent), Some(Span::from(0_u32..10)));
+		assert_eq!(Span::from(8_u32..15).relative_span(parent), None);
+		assert_eq!(Span::from(15_u32..21).relative_span(parent), None);
+
+		let span = Span::from(12_u32..15);
+		assert_eq!(
+			span.relative_span(parent)
+				.map(|span| span.absolute_from(parent.low)),
+			Some(span)
+		);
+	}
+
+	#[test]
+	fn intersection() {
+		let lhs = Span::new(Pos::from_u32(10), Pos::from_u32(20));
+
+		let rhs = Span::new(Pos::from_u32(15), Pos::from_u32(30));
+		assert_eq!(lhs.intersection(rhs), Some(Span::from(15_u32..20)));
+		assert_eq!(rhs.intersection(lhs), Some(Span::from(15_u32..20)));
+
+		let rhs = Span::new(Pos::from_u32(12), Pos::from_u32(14));
+		assert_eq!(lhs.intersection(rhs), Some(rhs));
+
+		let rhs = Span::new(Pos::from_u32(20), Pos::from_u32(30));
+		assert_eq!(lhs.intersection(rhs), None);
+
+		let rhs = Span::new(Pos::from_u32(25), Pos::from_u32(30));
+		assert_eq!(lhs.intersection(rhs), None);
+	}
+
+	#[test]
+	fn touching_intersection() {
+		let lhs = Span::new(Pos::from_u32(10), Pos::from_u32(20));
+
+		let rhs = Span::new(Pos::from_u32(15), Pos::from_u32(30));
+		assert_eq!(
+			lhs.touching_intersection(rhs),
+			Some(Span::from(15_u32..20))
+		);
+
+		let rhs = Span::new(Pos::from_u32(20), Pos::from_u32(30));
+		assert_eq!(
+			lhs.touching_intersection(rhs),
+			Some(Span::from(20_u32..20))
+		);
+		assert_eq!(
+			rhs.touching_intersection(lhs),
+			Some(Span::from(20_u32..20))
+		);
+
+		let rhs = Span::new(Pos::from_u32(25), Pos::from_u32(30));
+		assert_eq!(lhs.touching_intersection(rhs), None);
+	}
+
+	#[test]
+	fn overlap_len() {
+		let lhs = Span::new(Pos::from_u32(10), Pos::from_u32(20));
+
+		let rhs = Span::new(Pos::from_u32(15), Pos::from_u32(30));
+		assert_eq!(lhs.overlap_len(rhs), 5);
+
+		let rhs = Span::new(Pos::from_u32(20), Pos::from_u32(30));
+		assert_eq!(lhs.overlap_len(rhs), 0);
+
+		let rhs = Span::new(Pos::from_u32(25), Pos::from_u32(30));
+		assert_eq!(lhs.overlap_len(rhs), 0);
+	}
+
+	#[test]
+	fn hex() {
+		let span = Span::new(Pos::from_u32(0xbeef), Pos::from_u32(0xdead));
+		assert_eq!(format!("{:x}", span), "beef..dead");
+		assert_eq!(format!("{:#x}", span), "0xbeef..0xdead");
+		assert_eq!(format!("{:X}", span), "BEEF..DEAD");
+		assert_eq!(format!("{:#X}", span), "0xBEEF..0xDEAD");
+	}
+
 	#[test]
 	fn union() {
 		let span_lhs = Span::new(Pos::from_u32(50), Pos::from_u32(80));
@@ -341,4 +1464,105 @@ mod tests {
 		assert_eq!(union.low.as_u32(), 10);
 		assert_eq!(union.high.as_u32(), 120);
 	}
+
+	#[test]
+	fn to_inclusive_range() {
+		let span = Span::new(Pos::from_u32(10), Pos::from_u32(14));
+
+		let range = span.to_inclusive_u32_range();
+		assert!(range.clone().eq(span.to_u32_range()));
+		assert_eq!(range, 10..=13);
+
+		let range = span.to_inclusive_usize_range();
+		assert!(range.eq(span.to_usize_range()));
+
+		let range = span.to_inclusive_pos_range();
+		assert_eq!(*range.start(), span.to_pos_range().start);
+		assert_eq!(*range.end(), Pos::from_u32(13));
+
+		let span = Span::new(Pos::from_u32(10), Pos::from_u32(11));
+		assert_eq!(span.to_inclusive_u32_range(), 10..=10);
+	}
+
+	#[test]
+	#[should_panic]
+	fn to_inclusive_range_empty() {
+		let _ = Span::at(Pos::from_u32(10)).to_inclusive_u32_range();
+	}
+
+	#[test]
+	fn as_pair() {
+		let span = Span::new(Pos::from_u32(10), Pos::from_u32(20));
+		assert_eq!(span.as_pos_pair(), (Pos::from_u32(10), Pos::from_u32(20)));
+		assert_eq!(span.as_u32_pair(), (10, 20));
+		assert_eq!(span.as_usize_pair(), (10, 20));
+	}
+
+	#[test]
+	fn packed_u64() {
+		let span = Span::from(10_u32..20);
+		assert_eq!(span.to_packed_u64(), 10 << 32 | 20);
+		assert_eq!(Span::from_packed_u64(span.to_packed_u64()), span);
+
+		let span = Span::from(u32::MAX..u32::MAX);
+		assert_eq!(span.to_packed_u64(), u64::MAX);
+		assert_eq!(Span::from_packed_u64(u64::MAX), span);
+
+		let span = Span::from(0..u32::MAX);
+		assert_eq!(Span::from_packed_u64(span.to_packed_u64()), span);
+		assert_eq!(Span::from_packed_u64(0), Span::at(Pos::ZERO));
+
+		// Inverted bounds are switched.
+		assert_eq!(
+			Span::from_packed_u64(20 << 32 | 10),
+			Span::from(10_u32..20)
+		);
+	}
+
+	#[cfg(feature = "arbitrary")]
+	#[test]
+	fn arbitrary() {
+		use arbitrary::{Arbitrary, Unstructured};
+
+		let data: Vec<u8> = (0..=255).rev().chain(0..=255).collect();
+		let mut u = Unstructured::new(&data);
+
+		while !u.is_empty() {
+			let span = Span::arbitrary(&mut u).unwrap();
+			assert!(span.low <= span.high);
+		}
+	}
+
+	#[cfg(feature = "serde")]
+	#[test]
+	fn serde() {
+		let span = Span::new(Pos::from_u32(1), Pos::from_u32(4));
+
+		let json = serde_json::to_string(&span).unwrap();
+		assert_eq!(json, r#"{"low":1,"high":4}"#);
+		assert_eq!(serde_json::from_str::<Span>(&json).unwrap(), span);
+
+		let json = r#"{"low":4,"high":1}"#;
+		assert_eq!(serde_json::from_str::<Span>(json).unwrap(), span);
+	}
+
+	#[cfg(feature = "serde")]
+	#[test]
+	fn serde_array() {
+		#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+		struct Token {
+			#[serde(with = "super::serde_array")]
+			span: Span,
+		}
+
+		let token =
+			Token { span: Span::new(Pos::from_u32(1), Pos::from_u32(4)) };
+
+		let json = serde_json::to_string(&token).unwrap();
+		assert_eq!(json, r#"{"span":[1,4]}"#);
+		assert_eq!(serde_json::from_str::<Token>(&json).unwrap(), token);
+
+		let token: Token = serde_json::from_str(r#"{"span":[4,1]}"#).unwrap();
+		assert_eq!(token.span, Span::from(1_u32..4));
+	}
 }
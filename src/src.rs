@@ -1,7 +1,14 @@
-use std::path::PathBuf;
+use std::collections::hash_map::DefaultHasher;
+use std::convert::Infallible;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 use crate::loc::Loc;
 use crate::pos::Pos;
+use crate::span::Span;
 
 /// Determins the origin from which a [`Source`] came from.
 ///
@@ -18,6 +25,65 @@ pub enum Origin {
 	Unknown,
 }
 
+impl Origin {
+	/// Returns `true` if the origin is an [`Origin::Path`].
+	pub const fn is_path(&self) -> bool {
+		matches!(self, Self::Path(_))
+	}
+
+	/// Returns `true` if the origin is an [`Origin::Named`].
+	pub const fn is_named(&self) -> bool {
+		matches!(self, Self::Named(_))
+	}
+
+	/// Returns `true` if the origin is [`Origin::Unknown`].
+	pub const fn is_unknown(&self) -> bool {
+		matches!(self, Self::Unknown)
+	}
+}
+
+impl fmt::Display for Origin {
+	/// Formats the origin for display.
+	///
+	/// Virtual origins (see [`Source::is_virtual`]) are wrapped in angle
+	/// brackets to distinguish them from real paths.
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Path(path) => write!(f, "{}", path.display()),
+			Self::Named(name) => write!(f, "<{}>", name),
+			Self::Unknown => f.write_str("<unknown>"),
+		}
+	}
+}
+
+impl From<PathBuf> for Origin {
+	/// Creates an [`Origin::Path`].
+	fn from(value: PathBuf) -> Self {
+		Self::Path(value)
+	}
+}
+
+impl From<&Path> for Origin {
+	/// Creates an [`Origin::Path`].
+	fn from(value: &Path) -> Self {
+		Self::Path(value.to_path_buf())
+	}
+}
+
+impl From<String> for Origin {
+	/// Creates an [`Origin::Named`].
+	fn from(value: String) -> Self {
+		Self::Named(value)
+	}
+}
+
+impl From<&str> for Origin {
+	/// Creates an [`Origin::Named`].
+	fn from(value: &str) -> Self {
+		Self::Named(value.to_owned())
+	}
+}
+
 /// A source for which to show/attach diagnostics.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Source {
@@ -29,14 +95,21 @@ pub struct Source {
 
 	/// Indices for each line start.
 	line_indices: Vec<usize>,
+
+	/// Whether the data only contains ASCII characters.
+	is_ascii: bool,
+
+	/// Position at which the data starts in its origin.
+	offset: Pos,
 	// TODO: special width chars to get correct index when printing
 }
 
 impl Source {
 	pub fn new(origin: Origin, data: String) -> Self {
 		let line_indices = scan_lines(&data);
+		let is_ascii = data.is_ascii();
 
-		Self { origin, data, line_indices }
+		Self { origin, data, line_indices, is_ascii, offset: Pos::ZERO }
 	}
 
 	pub fn from_file(path: PathBuf) -> Result<Self, std::io::Error> {
@@ -44,43 +117,2226 @@ impl Source {
 		Ok(Self::new(Origin::Path(path), data))
 	}
 
+	/// Creates a new source from the given (zero indexed) lines of a file.
+	///
+	/// The lines are kept as they are in the file, including their line
+	/// breaks. Lines past the end of the file are ignored. The file is read
+	/// line by line, so only the requested lines are kept in memory.
+	///
+	/// The position of the first kept line in the file is available with
+	/// [`Source::offset`].
+	pub fn from_file_lines(
+		path: PathBuf,
+		lines: Range<usize>,
+	) -> Result<Self, std::io::Error> {
+		use std::io::BufRead;
+
+		let mut reader = std::io::BufReader::new(std::fs::File::open(&path)?);
+		let mut data = String::new();
+		let mut offset = 0;
+		let mut line = String::new();
+
+		for index in 0..lines.end {
+			line.clear();
+
+			let read = reader.read_line(&mut line)?;
+			if read == 0 {
+				break;
+			}
+
+			if index < lines.start {
+				offset += read;
+			} else {
+				data.push_str(&line);
+			}
+		}
+
+		let mut source = Self::new(Origin::Path(path), data);
+		source.offset = Pos::from_usize(offset);
+
+		Ok(source)
+	}
+
+	/// Creates a new source with an [`Origin::Named`] origin.
+	pub fn from_str_named(
+		name: impl Into<String>,
+		data: impl Into<String>,
+	) -> Self {
+		Self::new(Origin::Named(name.into()), data.into())
+	}
+
+	/// Creates a new source with the same origin by transforming the data with
+	/// the given function.
+	///
+	/// # Note
+	///
+	/// Positions are not preserved across the transformation, spans of this
+	/// source may not be valid for the new one.
+	pub fn map(&self, f: impl FnOnce(&str) -> String) -> Self {
+		Self::new(self.origin.clone(), f(&self.data))
+	}
+
+	/// Creates a new source with the same origin which only contains the
+	/// lines for which `pred` returns `true`.
+	///
+	/// The predicate receives the text of each line (see
+	/// [`Source::iter_lines`]) and the kept lines are joined by `\n`.
+	///
+	/// # Note
+	///
+	/// Positions are not preserved, spans of this source can not be mapped
+	/// to the new one or back.
+	pub fn retain_lines(&self, pred: impl Fn(&str) -> bool) -> Self {
+		let lines: Vec<_> =
+			self.iter_lines().filter(|line| pred(line)).collect();
+
+		Self::new(self.origin.clone(), lines.join("\n"))
+	}
+
+	/// Creates a new source with the text of the span replaced by `with`.
+	///
+	/// The span is adjusted like in [`Source::apply_edit`]. The origin and
+	/// offset (see [`Source::offset`]) are kept. Positions before the span
+	/// stay valid for the new source, positions after it are shifted by the
+	/// change in length.
+	pub fn replace_span(&self, span: Span, with: &str) -> Self {
+		let mut source = self.clone();
+		source.apply_edit(span, with);
+
+		source
+	}
+
+	/// Concatenates the given sources into a single one.
+	///
+	/// The data is joined as is, without adding any line breaks in between.
+	/// Returns the merged source with an [`Origin::Unknown`] origin together
+	/// with the offset of each part in it. Spans of a part can be translated
+	/// into the merged source with [`Span::absolute_from`].
+	pub fn concat(parts: &[Self]) -> (Self, Vec<Pos>) {
+		let mut data = String::with_capacity(
+			parts.iter().map(|part| part.data.len()).sum(),
+		);
+		let mut line_indices = vec![0];
+		let mut offsets = Vec::with_capacity(parts.len());
+
+		for part in parts {
+			let offset = data.len();
+
+			offsets.push(Pos::from_usize(offset));
+			data.push_str(&part.data);
+
+			// Skip the first entry as it is not a line break.
+			line_indices.extend(
+				part.line_indices[1..].iter().map(|index| offset + index),
+			);
+		}
+
+		let is_ascii = parts.iter().all(|part| part.is_ascii);
+
+		(
+			Self {
+				origin: Origin::Unknown,
+				data,
+				line_indices,
+				is_ascii,
+				offset: Pos::ZERO,
+			},
+			offsets,
+		)
+	}
+
+	/// Rescans the line indices starting at the given position.
+	///
+	/// All line breaks before `pos` are kept as they are. The data can only
+	/// be changed from within the crate, so this is used by the mutating
+	/// methods (e.g. [`Source::truncate`]) to update the line indices after
+	/// the data was changed at or after `pos`.
+	///
+	/// The data after `pos` is also checked for non ASCII characters (see
+	/// [`Source::ascii_only`]). If the source was not ASCII only before, the
+	/// data before `pos` has to be checked as well.
+	pub(crate) fn reparse_lines_from(&mut self, pos: Pos) {
+		let start = pos.as_usize().min(self.data.len());
+
+		let keep = self.line_indices_before(start);
+		self.line_indices.truncate(keep);
+
+		let rest = &self.data.as_bytes()[start..];
+
+		self.line_indices.extend(
+			rest.iter()
+				.enumerate()
+				.filter(|(_, &byte)| byte == b'\n')
+				.map(|(index, _)| start + index),
+		);
+
+		self.is_ascii = rest.is_ascii()
+			&& (self.is_ascii || self.data.as_bytes()[..start].is_ascii());
+	}
+
+	/// Replaces the text of the span with `with` and returns the span of the
+	/// inserted text.
+	///
+	/// The span is clamped to the source and both ends are moved back to the
+	/// start of the character they point into.
+	///
+	/// The line indices are updated in place instead of rescanning the whole
+	/// data: Line breaks before the span are kept, the ones after it are
+	/// shifted by the change in length and only `with` is scanned for new
+	/// line breaks. The whole data is only checked for non ASCII characters
+	/// (see [`Source::ascii_only`]) if the edit removes the last of them.
+	pub fn apply_edit(&mut self, span: Span, with: &str) -> Span {
+		let start = self.floor_char_boundary(span.low.as_usize());
+		let end = self.floor_char_boundary(span.high.as_usize());
+
+		// Only removing non ASCII text can turn the data into ASCII.
+		let may_become_ascii = !self.is_ascii
+			&& with.is_ascii()
+			&& !self.data[start..end].is_ascii();
+
+		self.data.replace_range(start..end, with);
+
+		let keep = self.line_indices_before(start);
+		let after = self.line_indices_before(end);
+
+		for index in &mut self.line_indices[after..] {
+			*index = *index - end + start + with.len();
+		}
+
+		self.line_indices.splice(
+			keep..after,
+			with.bytes()
+				.enumerate()
+				.filter(|(_, byte)| *byte == b'\n')
+				.map(|(index, _)| start + index),
+		);
+
+		self.is_ascii = if may_become_ascii {
+			self.data.is_ascii()
+		} else {
+			self.is_ascii && with.is_ascii()
+		};
+
+		Span::from(start..start + with.len())
+	}
+
+	/// Shortens the source to end at the given position.
+	///
+	/// If the position is not on a character boundary it is moved back to the
+	/// start of the character. Positions past the end have no effect.
+	pub fn truncate(&mut self, pos: Pos) {
+		let len = self.floor_char_boundary(pos.as_usize());
+
+		self.data.truncate(len);
+		self.reparse_lines_from(Pos::from_usize(len));
+	}
+
+	/// Returns the position of the first byte at which the data of both
+	/// sources differ.
+	///
+	/// If one data is a prefix of the other, the end of the shorter one is
+	/// returned. Returns `None` if the data is identical.
+	pub fn diff_positions(&self, other: &Self) -> Option<Pos> {
+		let (lhs, rhs) = (self.data.as_bytes(), other.data.as_bytes());
+
+		lhs.iter()
+			.zip(rhs)
+			.position(|(a, b)| a != b)
+			.or_else(|| {
+				(lhs.len() != rhs.len()).then(|| lhs.len().min(rhs.len()))
+			})
+			.map(Pos::from_usize)
+	}
+
+	/// Returns the number of bytes at the end of the data which are the same
+	/// for both sources.
+	pub fn common_suffix_len(&self, other: &Self) -> usize {
+		let (lhs, rhs) = (self.data.as_bytes(), other.data.as_bytes());
+
+		lhs.iter()
+			.rev()
+			.zip(rhs.iter().rev())
+			.take_while(|(a, b)| a == b)
+			.count()
+	}
+
+	/// Returns the origin of the source.
+	pub const fn origin(&self) -> &Origin {
+		&self.origin
+	}
+
+	/// Returns the position in the origin at which the data of the source
+	/// starts.
+	///
+	/// This is only non zero for sources created from a part of a file (see
+	/// [`Source::from_file_lines`]). Spans of such a source can be mapped back
+	/// to the whole file with [`Span::absolute_from`].
+	pub const fn offset(&self) -> Pos {
+		self.offset
+	}
+
+	/// Returns the data of the source.
+	pub fn data(&self) -> &str {
+		&self.data
+	}
+
+	/// Returns `true` if the source does not originate from a file (e.g.
+	/// generated code).
+	pub const fn is_virtual(&self) -> bool {
+		!matches!(self.origin, Origin::Path(_))
+	}
+
+	/// Returns `true` if the data only contains ASCII characters.
+	///
+	/// In this case byte offsets and character offsets are the same. The flag
+	/// is computed once when the source is created.
+	pub const fn ascii_only(&self) -> bool {
+		self.is_ascii
+	}
+
+	/// Returns the text the given span covers.
+	///
+	/// Returns `None` if the span is out of range or does not lie on
+	/// character boundaries.
+	pub fn get(&self, span: Span) -> Option<&str> {
+		self.data.get(span.to_usize_range())
+	}
+
+	/// Returns the text the given span covers.
+	///
+	/// Unlike [`Source::get`] this never fails. The span is clamped to the
+	/// source and widened to include partial characters at either end.
+	pub fn slice_lossy(&self, span: Span) -> &str {
+		let low = self.floor_char_boundary(span.low.as_usize());
+		let high = self.ceil_char_boundary(span.high.as_usize());
+
+		&self.data[low..high.max(low)]
+	}
+
+	/// Splits the data at the given position into the text before and the
+	/// text starting at it.
+	///
+	/// Splitting at the end of the source returns an empty second half.
+	///
+	/// # Panics
+	///
+	/// This function will panic if the position is past the end of the
+	/// source or does not lie on a character boundary, like
+	/// [`str::split_at`]. Use [`Source::is_char_boundary`] to check the
+	/// position beforehand.
+	pub fn split_at_pos(&self, pos: Pos) -> (&str, &str) {
+		self.data.split_at(pos.as_usize())
+	}
+
+	/// Clamps both ends of the span to the length of the source.
+	pub fn clamp_to_source(&self, span: Span) -> Span {
+		let len = self.data.len();
+
+		Span::from(span.low.as_usize().min(len)..span.high.as_usize().min(len))
+	}
+
+	/// Returns the position directly after the span at which scanning can be
+	/// resumed.
+	///
+	/// The position is clamped to the end of the source.
+	pub fn position_after(&self, span: Span) -> Pos {
+		self.clamp_to_source(span).high
+	}
+
+	/// Returns the position at which the span starts.
+	pub fn position_before(&self, span: Span) -> Pos {
+		self.clamp_to_source(span).low
+	}
+
+	/// Returns `true` if the position is on a character boundary of the data.
+	///
+	/// Like [`str::is_char_boundary`], the start and end of the data are
+	/// boundaries, while positions past the end are not.
+	pub fn is_char_boundary(&self, pos: Pos) -> bool {
+		self.data.is_char_boundary(pos.as_usize())
+	}
+
+	/// Returns the span of the first occurrence of `needle`.
+	pub fn find(&self, needle: &str) -> Option<Span> {
+		let index = self.data.find(needle)?;
+		Some(Span::from(index..index + needle.len()))
+	}
+
+	/// Returns the span of the last occurrence of `needle`.
+	pub fn rfind(&self, needle: &str) -> Option<Span> {
+		let index = self.data.rfind(needle)?;
+		Some(Span::from(index..index + needle.len()))
+	}
+
+	/// Returns the position of the first character inside the span for which
+	/// `pred` returns `true`.
+	///
+	/// The span is clamped to the source. Returns `None` if no character
+	/// matches or the span does not lie on character boundaries.
+	pub fn find_in_span(
+		&self,
+		span: Span,
+		pred: impl Fn(char) -> bool,
+	) -> Option<Pos> {
+		let span = self.clamp_to_source(span);
+		let index = self.get(span)?.find(pred)?;
+
+		Some(span.low + index as u32)
+	}
+
+	/// Iterates over the characters inside the span together with their
+	/// absolute position.
+	///
+	/// The span is clamped to the source and shrunk to the nearest character
+	/// boundaries inside it, so only characters fully contained in the span
+	/// are returned.
+	pub fn char_indices_in(
+		&self,
+		span: Span,
+	) -> impl Iterator<Item = (Pos, char)> + '_ {
+		let low = self.ceil_char_boundary(span.low.as_usize());
+		let high = self.floor_char_boundary(span.high.as_usize());
+
+		self.data[low..high.max(low)]
+			.char_indices()
+			.map(move |(index, c)| (Pos::from_usize(low + index), c))
+	}
+
+	/// Returns the span starting at `start` which covers the next `char_len`
+	/// characters.
+	///
+	/// Returns `None` if `start` is out of range or not on a character
+	/// boundary, or if the source ends before `char_len` characters.
+	pub fn span_from_char_len(
+		&self,
+		start: Pos,
+		char_len: usize,
+	) -> Option<Span> {
+		let rest = self.data.get(start.as_usize()..)?;
+		let len = rest
+			.char_indices()
+			.map(|(index, _)| index)
+			.chain(std::iter::once(rest.len()))
+			.nth(char_len)?;
+
+		Some(Span::from_start_len(start, len as u32))
+	}
+
+	/// Returns the byte at the given position.
+	pub fn byte(&self, pos: Pos) -> Option<u8> {
+		self.data.as_bytes().get(pos.as_usize()).copied()
+	}
+
+	/// Returns the character starting at the given position.
+	///
+	/// Returns `None` if the position is past the end of the source or not
+	/// on a character boundary.
+	pub fn char_at(&self, pos: Pos) -> Option<char> {
+		self.data.get(pos.as_usize()..)?.chars().next()
+	}
+
+	/// Converts a position to a location.
+	///
+	/// The position directly after the last character (end of file) is
+	/// still considered valid as it is the exclusive end of any span reaching
+	/// to the end of the source.
 	pub fn pos_to_loc(&self, pos: Pos) -> Option<Loc> {
-		if pos.as_usize() >= self.data.len() {
+		if pos.as_usize() > self.data.len() {
 			return None;
 		}
 
-		let line_index = self
-			.line_indices
-			.binary_search(&pos.as_usize())
-			.map_or_else(|x| x, |x| x);
+		// The first entry is always `0`, all further entries are the indices
+		// of the line breaks. A line break belongs to the line it terminates.
+		let line_index = match self.line_indices.binary_search(&pos.as_usize())
+		{
+			Ok(index) => index.saturating_sub(1),
+			Err(index) => index - 1,
+		};
 
-		let line_pos = self.line_indices[line_index];
+		let line_pos = if line_index == 0 {
+			0
+		} else {
+			self.line_indices[line_index] + 1
+		};
 
 		let column_index = pos.as_usize() - line_pos;
 
 		Some(Loc::new(line_index, column_index))
 	}
-}
 
-fn scan_lines(mut data: &str) -> Vec<usize> {
-	let mut line_indices = Vec::new();
-	let mut offset = 0;
+	/// Formats the origin together with the location of the position as
+	/// `origin:line:column`, e.g. `src/main.rs:12:5`.
+	///
+	/// The origin is formatted with its [`Display`](fmt::Display)
+	/// implementation and the location is one indexed like in the
+	/// [`Display`](fmt::Display) implementation of [`Loc`]. Positions past the
+	/// end of the source are clamped to the end.
+	pub fn line_and_column_display(&self, pos: Pos) -> String {
+		let pos = pos.min(Pos::from_usize(self.data.len()));
+		let loc = self
+			.pos_to_loc(pos)
+			.expect("Clamped position must be inside the source");
 
-	while let Some(index) = data.find('\n') {
-		let absolute_index = offset + index;
+		format!("{}:{}", self.origin, loc)
+	}
 
-		line_indices.push(absolute_index);
+	/// Converts multiple positions to locations at once.
+	///
+	/// This is the same as calling [`Source::pos_to_loc`] for each position,
+	/// but the line indices are only walked once instead of searching them for
+	/// each position.
+	pub fn batch_locations(&self, positions: &[Pos]) -> Vec<Option<Loc>> {
+		let mut order: Vec<_> = (0..positions.len()).collect();
+		order.sort_unstable_by_key(|&index| positions[index]);
 
-		let skip_to_index = index + 1;
-		data = &data[skip_to_index..];
-		offset += skip_to_index;
+		let mut locs = vec![None; positions.len()];
+		let mut line = 0;
+
+		for index in order {
+			let pos = positions[index].as_usize();
+
+			if pos > self.data.len() {
+				// All remaining positions are out of range as well.
+				break;
+			}
+
+			// A line break belongs to the line it terminates.
+			while self.line_indices.get(line + 1).is_some_and(|&lb| lb < pos) {
+				line += 1;
+			}
+
+			let line_start = self
+				.line_range(line)
+				.expect("Line must exist as it contains `pos`")
+				.start;
+
+			locs[index] = Some(Loc::new(line, pos - line_start));
+		}
+
+		locs
 	}
 
-	line_indices
-}
+	/// Returns the column of the position in characters.
+	///
+	/// In contrast to [`Source::pos_to_loc`] and [`Source::column_bytes`],
+	/// which count bytes, this counts the characters between the start of the
+	/// line and the position. For ASCII only sources (see
+	/// [`Source::ascii_only`]) both are the same and the character counting is
+	/// skipped.
+	///
+	/// Returns `None` if the position is out of range or not on a character
+	/// boundary.
+	pub fn column_at(&self, pos: Pos) -> Option<usize> {
+		let loc = self.pos_to_loc(pos)?;
+
+		if self.is_ascii {
+			return Some(loc.column);
+		}
+
+		let line_start = self.line_range(loc.line)?.start;
+
+		Some(self.data.get(line_start..pos.as_usize())?.chars().count())
+	}
+
+	/// Converts a location back to a position.
+	///
+	/// This is the inverse of [`Source::pos_to_loc`], the column is counted
+	/// in bytes. Returns `None` if the location is not valid (see
+	/// [`Source::validate_loc`]).
+	pub fn loc_to_pos(&self, loc: Loc) -> Option<Pos> {
+		let range = self.line_range(loc.line)?;
+
+		self.data[range.clone()]
+			.is_char_boundary(loc.column)
+			.then(|| Pos::from_usize(range.start + loc.column))
+	}
+
+	/// Converts multiple locations to positions at once.
+	///
+	/// This is the same as calling [`Source::loc_to_pos`] for each location.
+	/// The start of each line is looked up directly from the line indices,
+	/// so no searching is needed regardless of the order of the locations.
+	pub fn locs_to_positions(&self, locs: &[Loc]) -> Vec<Option<Pos>> {
+		locs.iter().map(|&loc| self.loc_to_pos(loc)).collect()
+	}
+
+	/// Returns the column of the position in bytes.
+	///
+	/// This is the number of bytes between the start of the line and the
+	/// position, the same as the column returned by [`Source::pos_to_loc`].
+	/// Unlike [`Source::column_at`] it does not count characters, so it also
+	/// works for positions inside a character and is cheap to compute.
+	/// Positions past the end of the source are clamped to the end.
+	pub fn column_bytes(&self, pos: Pos) -> usize {
+		let pos = pos.min(Pos::from_usize(self.data.len()));
+
+		self.pos_to_loc(pos)
+			.expect("Clamped position must be inside the source")
+			.column
+	}
+
+	/// Returns `true` if the location addresses an existing line and a column
+	/// inside it.
+	///
+	/// Like the locations returned by [`Source::pos_to_loc`], the column is
+	/// counted in bytes. It is valid if it lies on a character boundary and
+	/// at most at the end of the line (excluding the line break).
+	pub fn validate_loc(&self, loc: Loc) -> bool {
+		self.line(loc.line)
+			.is_some_and(|text| text.is_char_boundary(loc.column))
+	}
+
+	/// Returns the data of the source with each tab expanded to spaces up to
+	/// the next multiple of `tab_width`.
+	///
+	/// Columns are counted in characters and reset after each line break. A
+	/// `tab_width` of `0` removes all tabs.
+	pub fn replace_tabs(&self, tab_width: usize) -> String {
+		let mut expanded = String::with_capacity(self.data.len());
+		let mut column = 0;
+
+		for c in self.data.chars() {
+			match c {
+				'\t' if tab_width == 0 => {}
+				'\t' => {
+					let width = tab_width - column % tab_width;
+					expanded.extend(std::iter::repeat_n(' ', width));
+					column += width;
+				}
+				'\n' => {
+					expanded.push(c);
+					column = 0;
+				}
+				_ => {
+					expanded.push(c);
+					column += 1;
+				}
+			}
+		}
+
+		expanded
+	}
+
+	/// Returns the number of columns the text of the span takes up when
+	/// displayed with tabs expanded like in [`Source::replace_tabs`].
+	///
+	/// Only the first line of the span is measured, the part after its line
+	/// break is ignored. Tab stops are computed from the start of that line.
+	/// Returns `0` if the span is out of range or does not lie on character
+	/// boundaries.
+	pub fn columns_width(&self, span: Span, tab_width: usize) -> usize {
+		let Some(loc) = self.pos_to_loc(span.low) else {
+			return 0;
+		};
+		let Some(line) = self.line(loc.line) else {
+			return 0;
+		};
+
+		let low = loc.column;
+		let high = (low + span.len() as usize).min(line.len());
+
+		match (line.get(..low), line.get(..high)) {
+			(Some(before), Some(through)) => {
+				expanded_width(through, tab_width)
+					- expanded_width(before, tab_width)
+			}
+			_ => 0,
+		}
+	}
+
+	/// Returns the position of the character displayed at the given visual
+	/// column of a line, with tabs expanded like in [`Source::replace_tabs`].
+	///
+	/// This is the inverse of [`Source::columns_width`]. A column inside of an
+	/// expanded tab maps to the tab itself. The column directly after the
+	/// last character maps to the end of the line. Returns `None` if the line
+	/// does not exist or the column lies further past the end of the line.
+	pub fn visible_width_to_pos(
+		&self,
+		line: usize,
+		visual_col: usize,
+		tab_width: usize,
+	) -> Option<Pos> {
+		let range = self.line_range(line)?;
+		let mut column = 0;
+
+		for (index, c) in self.data[range.clone()].char_indices() {
+			let width = match c {
+				'\t' if tab_width == 0 => 0,
+				'\t' => tab_width - column % tab_width,
+				_ => 1,
+			};
+
+			if visual_col < column + width {
+				return Some(Pos::from_usize(range.start + index));
+			}
+
+			column += width;
+		}
+
+		(visual_col == column).then(|| Pos::from_usize(range.end))
+	}
+
+	/// Returns the range of (zero indexed) lines the given span covers.
+	///
+	/// An empty span covers the line it sits on. Returns `None` if the span
+	/// reaches past the end of the source.
+	pub fn lines_of_span(&self, span: Span) -> Option<Range<usize>> {
+		if span.high.as_usize() > self.data.len() {
+			return None;
+		}
+
+		let first = self.pos_to_loc(span.low)?.line;
+
+		let last = if span.low == span.high {
+			first
+		} else {
+			self.pos_to_loc(span.high - 1)?.line
+		};
+
+		Some(first..last + 1)
+	}
+
+	/// Returns the number of lines the span covers (see
+	/// [`Source::lines_of_span`]).
+	///
+	/// Returns `0` if the span reaches past the end of the source.
+	pub fn count_lines_in(&self, span: Span) -> usize {
+		self.lines_of_span(span).map_or(0, |lines| lines.len())
+	}
+
+	/// Returns an iterator over each line the given span covers together
+	/// with the location at which the span starts on that line.
+	///
+	/// For all but the first line this is the first column of the line.
+	pub fn span_line_locs(
+		&self,
+		span: Span,
+	) -> impl Iterator<Item = (usize, Loc)> {
+		let start = self.pos_to_loc(span.low);
+
+		self.lines_of_span(span).into_iter().flatten().map(move |line| {
+			let loc = match start {
+				Some(start) if start.line == line => start,
+				_ => Loc::new(line, 0),
+			};
+
+			(line, loc)
+		})
+	}
+
+	/// Splits the span into the parts on each line it covers.
+	///
+	/// Each part is paired with its (zero indexed) line and is relative to
+	/// the start of that line. Line breaks are not part of any line. Returns
+	/// an empty list if the span reaches past the end of the source.
+	pub fn split_span_at_lines(&self, span: Span) -> Vec<(usize, Span)> {
+		let Some(lines) = self.lines_of_span(span) else {
+			return Vec::new();
+		};
+
+		lines
+			.map(|line| {
+				let range = self
+					.line_range(line)
+					.expect("Line must exist as the span covers it");
+				let local = |pos: Pos| {
+					pos.as_usize().clamp(range.start, range.end) - range.start
+				};
+
+				(line, Span::from(local(span.low)..local(span.high)))
+			})
+			.collect()
+	}
+
+	/// Groups the spans into windows of whole lines.
+	///
+	/// The line ranges of the spans (see [`Source::lines_of_span`]) are merged
+	/// if they overlap or are directly adjacent. Each window spans from the
+	/// start of its first line to the end of its last line (excluding the
+	/// line break). Spans reaching past the end of the source are ignored.
+	pub fn windows_of_spans(&self, spans: &[Span]) -> Vec<Span> {
+		let mut ranges: Vec<_> = spans
+			.iter()
+			.filter_map(|&span| self.lines_of_span(span))
+			.collect();
+		ranges.sort_unstable_by_key(|range| range.start);
+
+		let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+
+		for range in ranges {
+			match merged.last_mut() {
+				Some(last) if range.start <= last.end => {
+					last.end = last.end.max(range.end);
+				}
+				_ => merged.push(range),
+			}
+		}
+
+		merged
+			.into_iter()
+			.map(|lines| {
+				self.span_of_line_range(lines).expect("Lines must exist")
+			})
+			.collect()
+	}
+
+	/// Returns the line containing the start of the span with a marker line
+	/// underneath which underlines the span with `^`.
+	///
+	/// The span is clamped to the source and the underline to the line. An
+	/// empty span is marked with a single `^`.
+	pub fn visualize(&self, span: Span) -> String {
+		let len = self.data.len();
+		let low = span.low.as_usize().min(len);
+		let high = span.high.as_usize().min(len);
+
+		let line = self
+			.pos_to_loc(Pos::from_usize(low))
+			.and_then(|loc| self.line_range(loc.line))
+			.expect("Clamped position must be inside the source");
+
+		let text = &self.data[line.clone()];
+		let start = low - line.start;
+		let end = high.min(line.end).max(low) - line.start;
+		let marker = underline(text, start..end, '^');
+
+		format!("{}\n{}", text, marker)
+	}
+
+	/// Returns the span of the leading whitespace of the given line.
+	///
+	/// If the line is empty or only consists of whitespace, an empty span at
+	/// the start of the line is returned.
+	pub fn line_indent(&self, line: usize) -> Option<Span> {
+		let range = self.line_range(line)?;
+		let text = &self.data[range.clone()];
+
+		let indent = text.find(|c: char| !c.is_whitespace()).unwrap_or(0);
+
+		Some(Span::from(range.start..range.start + indent))
+	}
+
+	/// Converts a position to a location where the column counts extended
+	/// grapheme clusters instead of bytes.
+	///
+	/// Returns `None` if the position is out of range or not on a character
+	/// boundary.
+	#[cfg(feature = "unicode-segmentation")]
+	#[cfg_attr(docsrs, doc(cfg(feature = "unicode-segmentation")))]
+	pub fn grapheme_location(&self, pos: Pos) -> Option<Loc> {
+		use unicode_segmentation::UnicodeSegmentation;
+
+		let line = self.pos_to_loc(pos)?.line;
+		let line_start = self.line_range(line)?.start;
+		let column =
+			self.data.get(line_start..pos.as_usize())?.graphemes(true).count();
+
+		Some(Loc::new(line, column))
+	}
+
+	/// Converts only [`Span::low`] of the given span to a location.
+	///
+	/// # Panics
+	///
+	/// This function will panic if [`Span::low`] lies past the end of the
+	/// source.
+	pub fn span_start_loc(&self, span: Span) -> Loc {
+		self.pos_to_loc(span.low)
+			.expect("Span start must not lie past the end of the source")
+	}
+
+	/// Converts only [`Span::high`] of the given span to a location.
+	///
+	/// # Panics
+	///
+	/// This function will panic if [`Span::high`] lies past the end of the
+	/// source.
+	pub fn span_end_loc(&self, span: Span) -> Loc {
+		self.pos_to_loc(span.high)
+			.expect("Span end must not lie past the end of the source")
+	}
+
+	/// Returns an empty span at the end of the source.
+	pub const fn eof_span(&self) -> Span {
+		Span::at(Pos::from_usize(self.byte_len()))
+	}
+
+	/// Returns the length of the source in bytes.
+	pub const fn byte_len(&self) -> usize {
+		self.data.len()
+	}
+
+	/// Returns the span covering the given lines.
+	///
+	/// Like [`Source::line_span`], the line break of the last line is not
+	/// included. Returns `None` if the range is empty or any line is out of
+	/// range.
+	pub fn span_of_line_range(&self, lines: Range<usize>) -> Option<Span> {
+		if lines.is_empty() {
+			return None;
+		}
+
+		let start = self.line_range(lines.start)?.start;
+		let end = self.line_range(lines.end - 1)?.end;
+
+		Some(Span::from(start..end))
+	}
+
+	/// Returns the byte offset at which the given line starts.
+	pub fn byte_offset_of_line(&self, line: usize) -> Option<usize> {
+		self.line_range(line).map(|range| range.start)
+	}
+
+	/// Returns the start of the line before the one containing `pos`.
+	///
+	/// Returns `None` if `pos` is on the first line or out of range.
+	pub fn prev_line_start(&self, pos: Pos) -> Option<Pos> {
+		let line = self.pos_to_loc(pos)?.line.checked_sub(1)?;
+
+		self.byte_offset_of_line(line).map(Pos::from_usize)
+	}
+
+	/// Returns the start of the line after the one containing `pos`.
+	///
+	/// Returns `None` if `pos` is on the last line or out of range.
+	pub fn next_line_start(&self, pos: Pos) -> Option<Pos> {
+		let line = self.pos_to_loc(pos)?.line + 1;
+
+		self.byte_offset_of_line(line).map(Pos::from_usize)
+	}
+
+	/// Returns the span of the given line excluding the line break.
+	pub fn line_span(&self, line: usize) -> Option<Span> {
+		self.line_range(line).map(Span::from)
+	}
+
+	/// Returns the text of the given line excluding the line break.
+	pub fn line(&self, line: usize) -> Option<&str> {
+		self.line_range(line).map(|range| &self.data[range])
+	}
+
+	/// Returns the number of lines.
+	///
+	/// The (possibly empty) text after the last line break counts as a line.
+	pub const fn line_count(&self) -> usize {
+		self.line_indices.len()
+	}
+
+	/// Returns `true` if the data ends with a line break (`\n` or `\r\n`).
+	///
+	/// In this case the last line (see [`Source::line_count`]) is empty.
+	pub fn ends_with_newline(&self) -> bool {
+		self.data.ends_with('\n')
+	}
+
+	/// Returns an iterator over the text of each line excluding the line
+	/// break.
+	pub fn iter_lines(&self) -> impl Iterator<Item = &str> {
+		self.data.split('\n')
+	}
+
+	/// Returns the text of each line excluding the line break as owned
+	/// strings (see [`Source::iter_lines`]).
+	pub fn to_owned_lines(&self) -> Vec<String> {
+		self.iter_lines().map(str::to_owned).collect()
+	}
+
+	/// Returns an iterator over the span of each line (see
+	/// [`Source::line_span`]).
+	pub fn each_line_span(&self) -> impl Iterator<Item = Span> + '_ {
+		(0..self.line_count()).map(|line| {
+			self.line_span(line).expect("Line must exist as it is in range")
+		})
+	}
+
+	/// Returns the span of each line (see [`Source::line_span`]).
+	pub fn line_spans(&self) -> Vec<Span> {
+		self.each_line_span().collect()
+	}
+
+	/// Returns a hash of the text of each line excluding the line break.
+	///
+	/// The hashes are only meant to be compared with hashes computed by the
+	/// same build of the program, see [`Source::changed_lines`].
+	pub fn hash_lines(&self) -> Vec<u64> {
+		self.iter_lines()
+			.map(|line| {
+				let mut hasher = DefaultHasher::new();
+				line.hash(&mut hasher);
+				hasher.finish()
+			})
+			.collect()
+	}
+
+	/// Returns the (zero indexed) lines which differ between both sources.
+	///
+	/// Lines are compared by their hash (see [`Source::hash_lines`]) at the
+	/// same index, so inserting a line reports all following lines as
+	/// changed. Lines only present in one of the sources count as changed.
+	pub fn changed_lines(&self, other: &Self) -> Vec<usize> {
+		let (lhs, rhs) = (self.hash_lines(), other.hash_lines());
+
+		(0..lhs.len().max(rhs.len()))
+			.filter(|&line| lhs.get(line) != rhs.get(line))
+			.collect()
+	}
+
+	/// Returns the length of the longest line in characters, excluding the
+	/// line break.
+	pub fn longest_line_len(&self) -> usize {
+		self.iter_lines()
+			.map(
+				|line| {
+					if self.is_ascii {
+						line.len()
+					} else {
+						line.chars().count()
+					}
+				},
+			)
+			.max()
+			.unwrap_or(0)
+	}
+
+	/// Returns the lines covered by the span together with up to `before`
+	/// lines before and `after` lines after it as `(line, text)` pairs.
+	///
+	/// Returns an empty list if the span reaches past the end of the source.
+	pub fn lines_around(
+		&self,
+		span: Span,
+		before: usize,
+		after: usize,
+	) -> Vec<(usize, &str)> {
+		let Some(lines) = self.lines_of_span(span) else {
+			return Vec::new();
+		};
+
+		let start = lines.start.saturating_sub(before);
+		let end = lines.end.saturating_add(after).min(self.line_count());
+
+		self.iter_lines().enumerate().skip(start).take(end - start).collect()
+	}
+
+	/// Returns the text of the given line with trailing whitespace (`\r`,
+	/// `\n` and spaces) removed together with the span of the kept text.
+	pub fn line_text_trimmed(&self, line: usize) -> Option<(Span, &str)> {
+		let range = self.line_range(line)?;
+		let text =
+			self.data[range.clone()].trim_end_matches(['\r', '\n', ' ']);
+
+		Some((Span::from(range.start..range.start + text.len()), text))
+	}
+
+	/// Returns the byte range of the given line excluding the line break.
+	fn line_range(&self, line: usize) -> Option<Range<usize>> {
+		let start = match line {
+			0 => 0,
+			_ => self.line_indices.get(line)? + 1,
+		};
+
+		let end = self
+			.line_indices
+			.get(line + 1)
+			.copied()
+			.unwrap_or(self.data.len());
+
+		Some(start..end)
+	}
+
+	/// Returns the number of line indices which lie before the given index.
+	///
+	/// The first entry is not a line break and is always counted, so this is
+	/// the number of entries to keep when the data changes at `index`.
+	fn line_indices_before(&self, index: usize) -> usize {
+		self.line_indices[1..].partition_point(|&lb| lb < index) + 1
+	}
+
+	/// Clamps the index to the data and moves it back to the start of the
+	/// character it points into.
+	fn floor_char_boundary(&self, index: usize) -> usize {
+		let mut index = index.min(self.data.len());
+
+		while !self.data.is_char_boundary(index) {
+			index -= 1;
+		}
+
+		index
+	}
+
+	/// Clamps the index to the data and moves it forward to the end of the
+	/// character it points into.
+	fn ceil_char_boundary(&self, index: usize) -> usize {
+		let mut index = index.min(self.data.len());
+
+		while !self.data.is_char_boundary(index) {
+			index += 1;
+		}
+
+		index
+	}
+}
+
+impl FromStr for Source {
+	type Err = Infallible;
+
+	/// Creates a new source with an [`Origin::Unknown`] origin.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Ok(Self::new(Origin::Unknown, s.to_owned()))
+	}
+}
+
+/// Creates a marker line for `text` which marks the given byte range with
+/// `mark`.
+///
+/// Tabs before the range are kept so that the marker aligns with `text` when
+/// displayed. An empty range is marked with a single `mark`.
+pub(crate) fn underline(
+	text: &str,
+	range: Range<usize>,
+	mark: char,
+) -> String {
+	let mut marker = String::new();
+
+	for (index, c) in text.char_indices() {
+		if index < range.start {
+			marker.push(if c == '\t' { '\t' } else { ' ' });
+		} else if index < range.end {
+			marker.push(mark);
+		}
+	}
+
+	if range.is_empty() {
+		marker.push(mark);
+	}
+
+	marker
+}
+
+/// Returns the number of columns a single line of text takes up with tabs
+/// expanded to the next multiple of `tab_width`.
+fn expanded_width(text: &str, tab_width: usize) -> usize {
+	text.chars().fold(0, |column, c| match c {
+		'\t' if tab_width == 0 => column,
+		'\t' => column + tab_width - column % tab_width,
+		_ => column + 1,
+	})
+}
+
+fn scan_lines(mut data: &str) -> Vec<usize> {
+	let mut line_indices = vec![0];
+	let mut offset = 0;
+
+	while let Some(index) = data.find('\n') {
+		let absolute_index = offset + index;
+
+		line_indices.push(absolute_index);
+
+		let skip_to_index = index + 1;
+		data = &data[skip_to_index..];
+		offset += skip_to_index;
+	}
+
+	line_indices
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn from_str() {
+		let source: Source = "a\nb".parse().unwrap();
+
+		assert_eq!(source.origin, Origin::Unknown);
+		assert_eq!(source.data, "a\nb");
+		assert_eq!(source.line_indices, vec![0, 1]);
+	}
+
+	#[test]
+	fn from_file_lines() {
+		const DATA: &str = "zero\none\ntwo\nthree\nfour\n";
+
+		let path = std::env::temp_dir()
+			.join(format!("ptk-from-file-lines-{}", std::process::id()));
+		std::fs::write(&path, DATA).unwrap();
+
+		let source = Source::from_file_lines(path.clone(), 2..4).unwrap();
+		let tail = Source::from_file_lines(path.clone(), 4..10).unwrap();
+		let empty = Source::from_file_lines(path.clone(), 7..10).unwrap();
+		std::fs::remove_file(&path).unwrap();
+
+		assert_eq!(source.origin(), &Origin::Path(path));
+		assert_eq!(source.data(), "two\nthree\n");
+		assert_eq!(source.offset(), Pos::from_u32(9));
+
+		let span = source.find("three").unwrap();
+		assert_eq!(&DATA[span.absolute_from(source.offset())], "three");
+
+		assert_eq!(tail.data(), "four\n");
+		assert_eq!(tail.offset(), Pos::from_u32(19));
+		assert_eq!(empty.data(), "");
+		assert_eq!(
+			Source::from_str("two").unwrap().offset(),
+			Pos::from_u32(0)
+		);
+	}
+
+	#[test]
+	fn from_str_named() {
+		let source = Source::from_str_named("stdin", "a\nb");
+
+		assert_eq!(source.origin, Origin::Named("stdin".into()));
+		assert_eq!(source.data, "a\nb");
+		assert_eq!(source.line_indices, vec![0, 1]);
+	}
+
+	#[test]
+	fn map() {
+		let source = Source::new(Origin::Named("a".into()), "ab\nc".into());
+		let mapped = source.map(|data| data.to_uppercase());
+
+		assert_eq!(mapped.data, "AB\nC");
+		assert_eq!(mapped.origin, Origin::Named("a".into()));
+		assert_eq!(source.data, "ab\nc");
+
+		let mapped = source.map(|data| data.replace('\n', "\n\n"));
+		assert_eq!(mapped.line_indices, super::scan_lines(&mapped.data));
+	}
+
+	#[test]
+	fn retain_lines() {
+		let source = Source::new(
+			Origin::Named("log".into()),
+			"first\n\n  \nsecond\n\nthird\n".into(),
+		);
+		let retained = source.retain_lines(|line| !line.trim().is_empty());
+
+		assert_eq!(retained.origin(), source.origin());
+		assert_eq!(retained.data(), "first\nsecond\nthird");
+		assert_eq!(retained.line_count(), 3);
+		assert_eq!(retained.line(2), Some("third"));
+
+		assert_eq!(source.retain_lines(|_| false).data(), "");
+	}
+
+	#[test]
+	fn replace_span() {
+		let source =
+			Source::new(Origin::Named("a".into()), "let x = 1;\nx".into());
+		let replaced = source.replace_span(Span::from(4_u32..5), "long\nname");
+
+		assert_eq!(source.data(), "let x = 1;\nx");
+		assert_eq!(source.line_count(), 2);
+		assert_eq!(source.line(1), Some("x"));
+
+		assert_eq!(replaced.origin(), source.origin());
+		assert_eq!(replaced.data(), "let long\nname = 1;\nx");
+		assert_eq!(
+			replaced,
+			Source::new(replaced.origin().clone(), replaced.data().into())
+		);
+		assert_eq!(replaced.line(1), Some("name = 1;"));
+
+		let source = Source::new(Origin::Unknown, "aäb".into());
+		assert_eq!(
+			source.replace_span(Span::from(2_u32..3), "c").data(),
+			"acb"
+		);
+		assert_eq!(
+			source.replace_span(Span::from(3_u32..9), "c").data(),
+			"aäc"
+		);
+	}
+
+	#[test]
+	fn concat() {
+		let first = Source::new(Origin::Unknown, "Hello\n".into());
+		let second = Source::new(Origin::Unknown, "big\nWorld\n".into());
+
+		let (merged, offsets) = Source::concat(&[first, second]);
+
+		assert_eq!(merged.data, "Hello\nbig\nWorld\n");
+		assert_eq!(merged.line_indices, super::scan_lines(&merged.data));
+		assert_eq!(offsets, vec![Pos::from_u32(0), Pos::from_u32(6)]);
+
+		let span = Span::from(4_u32..9).absolute_from(offsets[1]);
+		assert_eq!(merged.get(span), Some("World"));
+		assert_eq!(merged.span_start_loc(span), Loc::new(2, 0));
+	}
+
+	#[test]
+	fn reparse_lines_from() {
+		let mut source =
+			Source::new(Origin::Unknown, "Hello\nWorld\nfoo\n".into());
+
+		source.data.truncate(8);
+		source.data.push_str("\nbar\n\nbaz");
+		source.reparse_lines_from(Pos::from_u32(8));
+
+		assert_eq!(source.line_indices, super::scan_lines(&source.data));
+		assert_eq!(source.pos_to_loc(Pos::from_u32(9)), Some(Loc::new(2, 0)));
+		assert_eq!(source.pos_to_loc(Pos::from_u32(14)), Some(Loc::new(4, 0)));
+
+		source.data.insert(0, '\n');
+		source.reparse_lines_from(Pos::from_u32(0));
+
+		assert_eq!(source.line_indices, super::scan_lines(&source.data));
+		assert!(source.ascii_only());
+
+		source.data.push('ä');
+		source.reparse_lines_from(Pos::from_u32(18));
+		assert!(!source.ascii_only());
+
+		// The non ASCII character is before the changed data.
+		source.data.push('x');
+		source.reparse_lines_from(Pos::from_u32(20));
+		assert!(!source.ascii_only());
+
+		source.data.truncate(18);
+		source.reparse_lines_from(Pos::from_u32(18));
+		assert!(source.ascii_only());
+	}
+
+	#[test]
+	fn apply_edit() {
+		let mut source = Source::new(
+			Origin::Unknown,
+			"fn main() {\n\tlet x = 1;\n\tlet y = x;\n}\n".into(),
+		);
+		let edits: [(Span, &str); 7] = [
+			// Insert line breaks in the middle.
+			(Span::from(16_u32..21), "x =\n\n 1"),
+			// Remove line breaks.
+			(Span::from(3_u32..30), ""),
+			// Replace at the start.
+			(Span::from(0_u32..2), "äh\n"),
+			// Append at the end.
+			(Span::from(100_u32..100), "\nfn ü() {}"),
+			// Inside of a character.
+			(Span::from(1_u32..2), "e"),
+			(Span::from(0_u32..0), ""),
+			(Span::from(0_u32..100), "ascii\n"),
+		];
+
+		for (span, with) in edits {
+			let low = source.clamp_to_source(span).low;
+			let inserted = source.apply_edit(span, with);
+
+			assert_eq!(
+				source,
+				Source::new(Origin::Unknown, source.data.clone())
+			);
+			assert_eq!(source.get(inserted), Some(with));
+			assert!(inserted.low <= low);
+		}
+
+		assert_eq!(source.data, "ascii\n");
+		assert!(source.ascii_only());
+	}
+
+	#[test]
+	fn apply_edit_ascii_only() {
+		let mut source = Source::new(Origin::Unknown, "ä = ü;".into());
+
+		// Non ASCII text remains elsewhere.
+		source.apply_edit(Span::from(0_u32..2), "a");
+		assert!(!source.ascii_only());
+		source.apply_edit(Span::from(1_u32..2), "  ");
+		assert!(!source.ascii_only());
+
+		source.apply_edit(Span::from(5_u32..7), "b");
+		assert_eq!(source.data, "a  = b;");
+		assert!(source.ascii_only());
+
+		source.apply_edit(Span::from(0_u32..1), "é");
+		assert!(!source.ascii_only());
+	}
+
+	#[test]
+	fn truncate() {
+		let mut source =
+			Source::new(Origin::Unknown, "Hello\nWörld\nfoo".into());
+
+		source.truncate(Pos::from_u32(9));
+		assert_eq!(source.data, "Hello\nWö");
+		assert_eq!(source.line_indices, super::scan_lines(&source.data));
+		assert!(!source.ascii_only());
+		assert_eq!(source.pos_to_loc(Pos::from_u32(7)), Some(Loc::new(1, 1)));
+
+		source.truncate(Pos::from_u32(8));
+		assert_eq!(source.data, "Hello\nW");
+		assert!(source.ascii_only());
+
+		source.truncate(Pos::from_u32(6));
+		assert_eq!(source.data, "Hello\n");
+		assert_eq!(source.line_indices, vec![0, 5]);
+		assert_eq!(source.pos_to_loc(Pos::from_u32(6)), Some(Loc::new(1, 0)));
+
+		source.truncate(Pos::from_u32(5));
+		assert_eq!(source.data, "Hello");
+		assert_eq!(source.line_indices, vec![0]);
+		assert_eq!(source.pos_to_loc(Pos::from_u32(6)), None);
+	}
+
+	#[test]
+	fn diff_positions() {
+		let source = Source::new(Origin::Unknown, "let x = 1;".into());
+
+		let other = Source::new(Origin::Unknown, "let x = 1;".into());
+		assert_eq!(source.diff_positions(&other), None);
+		assert_eq!(source.common_suffix_len(&other), 10);
+
+		let other = Source::new(Origin::Unknown, "let y = 1;".into());
+		assert_eq!(source.diff_positions(&other), Some(Pos::from_u32(4)));
+		assert_eq!(source.common_suffix_len(&other), 5);
+
+		let other = Source::new(Origin::Unknown, "let x = 12;".into());
+		assert_eq!(source.diff_positions(&other), Some(Pos::from_u32(9)));
+		assert_eq!(source.common_suffix_len(&other), 1);
+
+		let other = Source::new(Origin::Unknown, "let x".into());
+		assert_eq!(source.diff_positions(&other), Some(Pos::from_u32(5)));
+		assert_eq!(source.common_suffix_len(&other), 0);
+	}
+
+	#[test]
+	fn origin_from() {
+		assert_eq!(
+			Origin::from(PathBuf::from("main.rs")),
+			Origin::Path("main.rs".into())
+		);
+		assert_eq!(
+			Origin::from(Path::new("main.rs")),
+			Origin::Path("main.rs".into())
+		);
+		assert_eq!(
+			Origin::from(String::from("main.rs")),
+			Origin::Named("main.rs".into())
+		);
+		assert_eq!(Origin::from("main.rs"), Origin::Named("main.rs".into()));
+	}
+
+	#[test]
+	fn origin_predicates() {
+		let path = Origin::Path(PathBuf::from("src/main.rs"));
+		assert!(path.is_path());
+		assert!(!path.is_named());
+		assert!(!path.is_unknown());
+
+		let named = Origin::Named("stdin".into());
+		assert!(!named.is_path());
+		assert!(named.is_named());
+		assert!(!named.is_unknown());
+
+		let unknown = Origin::Unknown;
+		assert!(!unknown.is_path());
+		assert!(!unknown.is_named());
+		assert!(unknown.is_unknown());
+	}
+
+	#[test]
+	fn is_virtual() {
+		let source =
+			Source::new(Origin::Path("main.rs".into()), String::new());
+		assert!(!source.is_virtual());
+		assert_eq!(source.origin().to_string(), "main.rs");
+
+		let source =
+			Source::new(Origin::Named("generated".into()), String::new());
+		assert!(source.is_virtual());
+		assert_eq!(source.origin().to_string(), "<generated>");
+
+		let source = Source::new(Origin::Unknown, String::new());
+		assert!(source.is_virtual());
+		assert_eq!(source.origin().to_string(), "<unknown>");
+	}
+
+	#[test]
+	fn get() {
+		let source = Source::new(Origin::Unknown, "café\n".into());
+
+		assert_eq!(source.get(Span::from(0_u32..3)), Some("caf"));
+		assert_eq!(source.get(Span::from(0_u32..4)), None);
+		assert_eq!(source.get(Span::from(0_u32..10)), None);
+	}
+
+	#[test]
+	fn slice_lossy() {
+		let source = Source::new(Origin::Unknown, "café\n".into());
+
+		assert_eq!(source.slice_lossy(Span::from(1_u32..3)), "af");
+		assert_eq!(source.slice_lossy(Span::from(3_u32..100)), "é\n");
+
+		// Partial characters are included as a whole.
+		assert_eq!(source.slice_lossy(Span::from(0_u32..4)), "café");
+		assert_eq!(source.slice_lossy(Span::from(4_u32..5)), "é");
+	}
+
+	#[test]
+	fn split_at_pos() {
+		let source = Source::new(Origin::Unknown, "let é = 1;".into());
+
+		assert_eq!(source.split_at_pos(Pos::from_u32(4)), ("let ", "é = 1;"));
+		assert_eq!(source.split_at_pos(Pos::from_u32(6)), ("let é", " = 1;"));
+		assert_eq!(source.split_at_pos(Pos::from_u32(0)), ("", "let é = 1;"));
+		assert_eq!(source.split_at_pos(Pos::from_u32(11)), ("let é = 1;", ""));
+	}
+
+	#[test]
+	#[should_panic]
+	fn split_at_pos_inside_char() {
+		let source = Source::new(Origin::Unknown, "let é = 1;".into());
+		let _ = source.split_at_pos(Pos::from_u32(5));
+	}
+
+	#[test]
+	#[should_panic]
+	fn split_at_pos_out_of_range() {
+		let source = Source::new(Origin::Unknown, "let é = 1;".into());
+		let _ = source.split_at_pos(Pos::from_u32(12));
+	}
+
+	#[test]
+	fn position_after_before() {
+		let source = Source::new(Origin::Unknown, "let x;".into());
+
+		let span = Span::from(4_u32..5);
+		assert_eq!(source.position_before(span), Pos::from_u32(4));
+		assert_eq!(source.position_after(span), Pos::from_u32(5));
+
+		let span = Span::from(5_u32..6);
+		assert_eq!(source.position_after(span), Pos::from_u32(6));
+
+		let span = Span::from(5_u32..10);
+		assert_eq!(source.position_after(span), Pos::from_u32(6));
+	}
+
+	#[test]
+	fn is_char_boundary() {
+		let source = Source::new(Origin::Unknown, "aé".into());
+
+		assert!(source.is_char_boundary(Pos::from_u32(0)));
+		assert!(source.is_char_boundary(Pos::from_u32(1)));
+		assert!(!source.is_char_boundary(Pos::from_u32(2)));
+		assert!(source.is_char_boundary(Pos::from_u32(3)));
+		assert!(!source.is_char_boundary(Pos::from_u32(4)));
+	}
+
+	#[test]
+	fn find() {
+		let source = Source::new(Origin::Unknown, "a; b; c".into());
+
+		assert_eq!(source.find(";"), Some(Span::from(1_u32..2)));
+		assert_eq!(source.find("b;"), Some(Span::from(3_u32..5)));
+		assert_eq!(source.find(":"), None);
+	}
+
+	#[test]
+	fn rfind() {
+		let source = Source::new(Origin::Unknown, "a; b; c".into());
+
+		assert_eq!(source.rfind(";"), Some(Span::from(4_u32..5)));
+		assert_eq!(source.rfind("a;"), Some(Span::from(0_u32..2)));
+		assert_eq!(source.rfind(":"), None);
+	}
+
+	#[test]
+	fn find_in_span() {
+		let source = Source::new(Origin::Unknown, "héllo wörld foo".into());
+
+		assert_eq!(
+			source.find_in_span(Span::from(0_u32..20), char::is_whitespace),
+			Some(Pos::from_u32(6))
+		);
+		assert_eq!(
+			source.find_in_span(Span::from(7_u32..20), char::is_whitespace),
+			Some(Pos::from_u32(13))
+		);
+		assert_eq!(
+			source.find_in_span(Span::from(7_u32..13), char::is_whitespace),
+			None
+		);
+		assert_eq!(source.find_in_span(Span::from(0_u32..2), |_| true), None);
+	}
+
+	#[test]
+	fn char_indices_in() {
+		let source = Source::new(Origin::Unknown, "a€ßb".into());
+		let chars = |span: Span| {
+			source
+				.char_indices_in(span)
+				.map(|(pos, c)| (pos.as_u32(), c))
+				.collect::<Vec<_>>()
+		};
+
+		assert_eq!(
+			chars(Span::from(0_u32..6)),
+			[(0, 'a'), (1, '€'), (4, 'ß')]
+		);
+		assert_eq!(
+			chars(Span::from(1_u32..100)),
+			[(1, '€'), (4, 'ß'), (6, 'b')]
+		);
+		// Partial characters at either end are skipped.
+		assert_eq!(chars(Span::from(2_u32..5)), []);
+		assert_eq!(chars(Span::from(2_u32..6)), [(4, 'ß')]);
+		assert_eq!(chars(Span::from(10_u32..20)), []);
+	}
+
+	#[test]
+	fn span_from_char_len() {
+		let source = Source::new(Origin::Unknown, "a€ßb".into());
+
+		assert_eq!(
+			source.span_from_char_len(Pos::from_u32(1), 2),
+			Some(Span::from(1_u32..6))
+		);
+		assert_eq!(
+			source.span_from_char_len(Pos::from_u32(0), 4),
+			Some(Span::from(0_u32..7))
+		);
+		assert_eq!(
+			source.span_from_char_len(Pos::from_u32(7), 0),
+			Some(Span::from(7_u32..7))
+		);
+		assert_eq!(source.span_from_char_len(Pos::from_u32(0), 5), None);
+		assert_eq!(source.span_from_char_len(Pos::from_u32(2), 1), None);
+		assert_eq!(source.span_from_char_len(Pos::from_u32(8), 0), None);
+	}
+
+	#[test]
+	fn byte() {
+		let source = Source::new(Origin::Unknown, "aé".into());
+
+		assert_eq!(source.byte(Pos::from_u32(0)), Some(b'a'));
+		assert_eq!(source.byte(Pos::from_u32(2)), Some(0xa9));
+		assert_eq!(source.byte(Pos::from_u32(3)), None);
+	}
+
+	#[test]
+	fn char_at() {
+		let source = Source::new(Origin::Unknown, "aé".into());
+
+		assert_eq!(source.char_at(Pos::from_u32(0)), Some('a'));
+		assert_eq!(source.char_at(Pos::from_u32(1)), Some('é'));
+		assert_eq!(source.char_at(Pos::from_u32(2)), None);
+		assert_eq!(source.char_at(Pos::from_u32(3)), None);
+	}
+
+	#[test]
+	fn pos_to_loc() {
+		let source = Source::new(Origin::Unknown, "Hello\nWorld\n".into());
+
+		assert_eq!(source.pos_to_loc(Pos::from_u32(0)), Some(Loc::new(0, 0)));
+		assert_eq!(source.pos_to_loc(Pos::from_u32(3)), Some(Loc::new(0, 3)));
+		assert_eq!(source.pos_to_loc(Pos::from_u32(5)), Some(Loc::new(0, 5)));
+		assert_eq!(source.pos_to_loc(Pos::from_u32(6)), Some(Loc::new(1, 0)));
+		assert_eq!(source.pos_to_loc(Pos::from_u32(11)), Some(Loc::new(1, 5)));
+		assert_eq!(source.pos_to_loc(Pos::from_u32(12)), Some(Loc::new(2, 0)));
+		assert_eq!(source.pos_to_loc(Pos::from_u32(13)), None);
+	}
+
+	#[cfg(feature = "unicode-segmentation")]
+	#[test]
+	fn grapheme_location() {
+		let source = Source::new(Origin::Unknown, "a\ne\u{301}x".into());
+
+		assert_eq!(source.pos_to_loc(Pos::from_u32(5)), Some(Loc::new(1, 3)));
+		assert_eq!(
+			source.grapheme_location(Pos::from_u32(5)),
+			Some(Loc::new(1, 1))
+		);
+		assert_eq!(
+			source.grapheme_location(Pos::from_u32(6)),
+			Some(Loc::new(1, 2))
+		);
+		assert_eq!(source.grapheme_location(Pos::from_u32(4)), None);
+		assert_eq!(source.grapheme_location(Pos::from_u32(7)), None);
+	}
+
+	#[test]
+	fn span_start_end_loc() {
+		let source = Source::new(Origin::Unknown, "Hello\nWorld\n".into());
+		let span = Span::from(3_u32..8);
+
+		assert_eq!(source.span_start_loc(span), Loc::new(0, 3));
+		assert_eq!(source.span_end_loc(span), Loc::new(1, 2));
+
+		let span = Span::from(6_u32..12);
+		assert_eq!(source.span_start_loc(span), Loc::new(1, 0));
+		assert_eq!(source.span_end_loc(span), Loc::new(2, 0));
+	}
+
+	#[test]
+	#[should_panic(expected = "Span end must not lie past the end")]
+	fn span_end_loc_past_end() {
+		let source = Source::new(Origin::Unknown, "Hello\nWorld\n".into());
+		let _ = source.span_end_loc(Span::from(6_u32..20));
+	}
+
+	#[test]
+	fn line_and_column_display() {
+		const DATA: &str = "fn main() {\n\tlet x = 1;\n}";
+
+		let path =
+			Source::new(Origin::Path("src/main.rs".into()), DATA.into());
+		assert_eq!(
+			path.line_and_column_display(Pos::from_u32(17)),
+			"src/main.rs:2:6"
+		);
+		assert_eq!(
+			path.line_and_column_display(Pos::from_u32(0)),
+			"src/main.rs:1:1"
+		);
+
+		let named = Source::from_str_named("stdin", DATA);
+		assert_eq!(
+			named.line_and_column_display(Pos::from_u32(17)),
+			"<stdin>:2:6"
+		);
+
+		let unknown = Source::from_str(DATA).unwrap();
+		assert_eq!(
+			unknown.line_and_column_display(Pos::from_u32(17)),
+			"<unknown>:2:6"
+		);
+		assert_eq!(
+			unknown.line_and_column_display(Pos::from_u32(99)),
+			"<unknown>:3:2"
+		);
+	}
+
+	#[test]
+	fn batch_locations() {
+		let source =
+			Source::new(Origin::Unknown, "\nHello\nWorld\n\nfoo".into());
+		let positions: Vec<_> = [7, 0, 3, 30, 6, 13, 14, 18, 0, 17, 1, 19, 12]
+			.into_iter()
+			.map(Pos::from_u32)
+			.collect();
+
+		let expected: Vec<_> =
+			positions.iter().map(|&pos| source.pos_to_loc(pos)).collect();
+
+		assert_eq!(source.batch_locations(&positions), expected);
+		assert_eq!(source.batch_locations(&[]), vec![]);
+	}
+
+	#[test]
+	fn ascii_only() {
+		let ascii = Source::new(Origin::Unknown, "let x\n  = 1;".into());
+		let utf8 = Source::new(Origin::Unknown, "let ä\n  = 1;".into());
+
+		assert!(ascii.ascii_only());
+		assert!(!utf8.ascii_only());
+		assert!(Source::concat(&[ascii.clone(), ascii.clone()])
+			.0
+			.ascii_only());
+		assert!(!Source::concat(&[ascii.clone(), utf8.clone()])
+			.0
+			.ascii_only());
+
+		let mut truncated = utf8.clone();
+		truncated.truncate(Pos::from_u32(4));
+		assert!(truncated.ascii_only());
+	}
+
+	#[test]
+	fn column_at() {
+		let ascii = Source::new(Origin::Unknown, "let x\n  = 1;".into());
+		let utf8 = Source::new(Origin::Unknown, "let ä\n  = ä;".into());
+
+		// Both paths agree on ASCII only data.
+		let ascii_as_utf8 = Source { is_ascii: false, ..ascii.clone() };
+		for pos in 0..=ascii.byte_len() + 1 {
+			let pos = Pos::from_usize(pos);
+			assert_eq!(ascii.column_at(pos), ascii_as_utf8.column_at(pos));
+			assert_eq!(
+				ascii.column_at(pos),
+				ascii.pos_to_loc(pos).map(|loc| loc.column)
+			);
+		}
+
+		assert_eq!(utf8.column_at(Pos::from_u32(6)), Some(5));
+		assert_eq!(utf8.column_at(Pos::from_u32(5)), None);
+		assert_eq!(utf8.column_at(Pos::from_u32(13)), Some(5));
+		assert_eq!(utf8.column_at(Pos::from_u32(14)), Some(6));
+		assert_eq!(utf8.column_at(Pos::from_u32(15)), None);
+	}
+
+	#[test]
+	fn loc_to_pos() {
+		let source = Source::new(Origin::Unknown, "ab\nçd\n".into());
+
+		assert_eq!(source.loc_to_pos(Loc::new(0, 0)), Some(Pos::from_u32(0)));
+		assert_eq!(source.loc_to_pos(Loc::new(0, 2)), Some(Pos::from_u32(2)));
+		assert_eq!(source.loc_to_pos(Loc::new(1, 2)), Some(Pos::from_u32(5)));
+		assert_eq!(source.loc_to_pos(Loc::new(1, 1)), None);
+		assert_eq!(source.loc_to_pos(Loc::new(0, 3)), None);
+		assert_eq!(source.loc_to_pos(Loc::new(2, 0)), Some(Pos::from_u32(7)));
+		assert_eq!(source.loc_to_pos(Loc::new(3, 0)), None);
+
+		for pos in 0..=source.byte_len() {
+			let pos = Pos::from_usize(pos);
+			if let Some(loc) = source.pos_to_loc(pos) {
+				if source.is_char_boundary(pos) {
+					assert_eq!(source.loc_to_pos(loc), Some(pos));
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn locs_to_positions() {
+		let source = Source::new(Origin::Unknown, "ab\nçd\n".into());
+		let locs = [
+			Loc::new(1, 2),
+			Loc::new(0, 0),
+			Loc::new(1, 1),
+			Loc::new(2, 0),
+			Loc::new(9, 0),
+			Loc::new(0, 1),
+		];
+
+		let batch = source.locs_to_positions(&locs);
+		let single: Vec<_> =
+			locs.iter().map(|&loc| source.loc_to_pos(loc)).collect();
+
+		assert_eq!(batch, single);
+		assert_eq!(
+			batch,
+			vec![
+				Some(Pos::from_u32(5)),
+				Some(Pos::from_u32(0)),
+				None,
+				Some(Pos::from_u32(7)),
+				None,
+				Some(Pos::from_u32(1))
+			]
+		);
+	}
+
+	#[test]
+	fn column_bytes() {
+		let source = Source::new(Origin::Unknown, "x\nlet é = ü;".into());
+
+		assert_eq!(source.column_bytes(Pos::from_u32(0)), 0);
+		assert_eq!(source.column_bytes(Pos::from_u32(6)), 4);
+		assert_eq!(source.column_bytes(Pos::from_u32(11)), 9);
+		assert_eq!(source.column_at(Pos::from_u32(11)), Some(8));
+		// Inside of `ü`.
+		assert_eq!(source.column_bytes(Pos::from_u32(12)), 10);
+		assert_eq!(source.column_at(Pos::from_u32(12)), None);
+		assert_eq!(source.column_bytes(Pos::from_u32(99)), 12);
+	}
+
+	#[test]
+	fn validate_loc() {
+		let source = Source::new(Origin::Unknown, "Hello\nWörld".into());
+
+		assert!(source.validate_loc(Loc::new(0, 0)));
+		assert!(source.validate_loc(Loc::new(0, 5)));
+		assert!(source.validate_loc(Loc::new(1, 3)));
+		assert!(!source.validate_loc(Loc::new(1, 2)));
+		assert!(!source.validate_loc(Loc::new(0, 6)));
+		assert!(!source.validate_loc(Loc::new(2, 0)));
+	}
+
+	#[test]
+	fn replace_tabs() {
+		let source =
+			Source::new(Origin::Unknown, "\tab\tc\td\n\tx\t\n".into());
+
+		assert_eq!(source.replace_tabs(4), "    ab  c   d\n    x   \n");
+		assert_eq!(source.replace_tabs(0), "abcd\nx\n");
+	}
+
+	#[test]
+	fn columns_width() {
+		let source =
+			Source::new(Origin::Unknown, "ab\tc = 1;\n\tlet é;\n".into());
+
+		// `\t` after `ab` only advances to the next tab stop.
+		assert_eq!(source.columns_width(Span::from(0_u32..4), 4), 5);
+		assert_eq!(source.columns_width(Span::from(2_u32..3), 4), 2);
+		assert_eq!(source.columns_width(Span::from(2_u32..3), 8), 6);
+		assert_eq!(source.columns_width(Span::from(2_u32..3), 0), 0);
+		assert_eq!(source.columns_width(Span::from(10_u32..19), 4), 10);
+		// Only the first line is measured.
+		assert_eq!(source.columns_width(Span::from(3_u32..15), 4), 6);
+		assert_eq!(source.columns_width(Span::from(16_u32..17), 4), 0);
+		assert_eq!(source.columns_width(Span::from(30_u32..31), 4), 0);
+	}
+
+	#[test]
+	fn visible_width_to_pos() {
+		let source = Source::new(Origin::Unknown, "a\tbé\n\tc".into());
+		let pos = |line, col| source.visible_width_to_pos(line, col, 4);
+
+		assert_eq!(pos(0, 0), Some(Pos::from_u32(0)));
+		// The tab covers the columns 1 to 3.
+		assert_eq!(pos(0, 1), Some(Pos::from_u32(1)));
+		assert_eq!(pos(0, 3), Some(Pos::from_u32(1)));
+		assert_eq!(pos(0, 4), Some(Pos::from_u32(2)));
+		// `é` is two bytes wide but takes up a single column.
+		assert_eq!(pos(0, 5), Some(Pos::from_u32(3)));
+		assert_eq!(pos(0, 6), Some(Pos::from_u32(5)));
+		assert_eq!(pos(0, 7), None);
+
+		assert_eq!(pos(1, 3), Some(Pos::from_u32(6)));
+		assert_eq!(pos(1, 4), Some(Pos::from_u32(7)));
+		assert_eq!(pos(1, 5), Some(Pos::from_u32(8)));
+		assert_eq!(pos(2, 0), None);
+
+		assert_eq!(
+			source.visible_width_to_pos(1, 0, 0),
+			Some(Pos::from_u32(7))
+		);
+
+		for col in 0..=6 {
+			let pos = pos(0, col).unwrap();
+			let width = source.columns_width(Span::from(0..pos.as_usize()), 4);
+			assert!(width <= col);
+		}
+	}
+
+	#[test]
+	fn lines_of_span() {
+		let source = Source::new(Origin::Unknown, "Hello\nWorld\n".into());
+
+		assert_eq!(source.lines_of_span(Span::from(1_u32..3)), Some(0..1));
+		assert_eq!(source.lines_of_span(Span::from(0_u32..6)), Some(0..1));
+		assert_eq!(source.lines_of_span(Span::from(3_u32..8)), Some(0..2));
+		assert_eq!(source.lines_of_span(Span::from(7_u32..7)), Some(1..2));
+		assert_eq!(source.lines_of_span(Span::from(7_u32..13)), None);
+	}
+
+	#[test]
+	fn count_lines_in() {
+		let source = Source::new(Origin::Unknown, "a\nb\nc\n".into());
+
+		assert_eq!(source.count_lines_in(Span::from(0_u32..1)), 1);
+		assert_eq!(source.count_lines_in(Span::from(0_u32..2)), 1);
+		assert_eq!(source.count_lines_in(Span::from(1_u32..5)), 3);
+		assert_eq!(source.count_lines_in(Span::from(2_u32..2)), 1);
+		assert_eq!(source.count_lines_in(Span::from(2_u32..20)), 0);
+	}
+
+	#[test]
+	fn span_line_locs() {
+		let source = Source::new(Origin::Unknown, "Hello\nWorld\n".into());
+		let locs: Vec<_> =
+			source.span_line_locs(Span::from(3_u32..8)).collect();
+
+		assert_eq!(locs, vec![(0, Loc::new(0, 3)), (1, Loc::new(1, 0))]);
+	}
+
+	#[test]
+	fn split_span_at_lines() {
+		let source =
+			Source::new(Origin::Unknown, "let a;\nlet bc;\nlet d;\n".into());
+		let parts = source.split_span_at_lines(Span::from(4_u32..19));
+
+		assert_eq!(
+			parts,
+			vec![
+				(0, Span::from(4_u32..6)),
+				(1, Span::from(0_u32..7)),
+				(2, Span::from(0_u32..4)),
+			]
+		);
+
+		let texts: Vec<_> = parts
+			.iter()
+			.map(|&(line, span)| &source.line(line).unwrap()[span])
+			.collect();
+		assert_eq!(texts, vec!["a;", "let bc;", "let "]);
+
+		assert_eq!(
+			source.split_span_at_lines(Span::from(8_u32..10)),
+			vec![(1, Span::from(1_u32..3))]
+		);
+		assert_eq!(source.split_span_at_lines(Span::from(20_u32..30)), vec![]);
+	}
+
+	#[test]
+	fn windows_of_spans() {
+		let source = Source::new(
+			Origin::Unknown,
+			"l0\nl1\nl2\nl3\nl4\nl5\nl6\n".into(),
+		);
+
+		let spans = [
+			Span::from(16_u32..17),
+			Span::from(9_u32..11),
+			Span::from(13_u32..13),
+			Span::from(0_u32..1),
+			Span::from(0_u32..100),
+		];
+
+		assert_eq!(
+			source.windows_of_spans(&spans),
+			vec![Span::from(0_u32..2), Span::from(9_u32..17)]
+		);
+		assert_eq!(source.windows_of_spans(&[]), vec![]);
+	}
+
+	#[test]
+	fn visualize() {
+		let source = Source::new(
+			Origin::Unknown,
+			"fn main() {\n\tlet x = 1;\n}".into(),
+		);
+
+		assert_eq!(
+			source.visualize(Span::from(17_u32..18)),
+			"\tlet x = 1;\n\t    ^"
+		);
+		assert_eq!(
+			source.visualize(Span::from(3_u32..20)),
+			"fn main() {\n   ^^^^^^^^"
+		);
+		assert_eq!(
+			source.visualize(Span::from(11_u32..11)),
+			"fn main() {\n           ^"
+		);
+	}
+
+	#[test]
+	fn line_indent() {
+		let source =
+			Source::new(Origin::Unknown, "fn main() {\n\t  x\n  \n\n}".into());
+
+		assert_eq!(source.line_indent(0), Some(Span::from(0_usize..0)));
+		assert_eq!(source.line_indent(1), Some(Span::from(12_usize..15)));
+		assert_eq!(source.line_indent(2), Some(Span::from(17_usize..17)));
+		assert_eq!(source.line_indent(3), Some(Span::from(20_usize..20)));
+		assert_eq!(source.line_indent(5), None);
+	}
+
+	#[test]
+	fn byte_offset_of_line() {
+		let source = Source::new(Origin::Unknown, "Hello\n\nWorld\n".into());
+
+		assert_eq!(source.byte_offset_of_line(0), Some(0));
+		assert_eq!(source.byte_offset_of_line(1), Some(6));
+		assert_eq!(source.byte_offset_of_line(2), Some(7));
+		assert_eq!(source.byte_offset_of_line(3), Some(13));
+		assert_eq!(source.byte_offset_of_line(4), None);
+	}
+
+	#[test]
+	fn prev_next_line_start() {
+		let source = Source::new(Origin::Unknown, "Hello\n\nWorld".into());
+
+		// First line.
+		assert_eq!(source.prev_line_start(Pos::from_u32(2)), None);
+		assert_eq!(
+			source.next_line_start(Pos::from_u32(2)),
+			Some(Pos::from_u32(6))
+		);
+		// Line break belongs to the line it terminates.
+		assert_eq!(
+			source.next_line_start(Pos::from_u32(5)),
+			Some(Pos::from_u32(6))
+		);
+
+		// Middle line.
+		assert_eq!(
+			source.prev_line_start(Pos::from_u32(6)),
+			Some(Pos::from_u32(0))
+		);
+		assert_eq!(
+			source.next_line_start(Pos::from_u32(6)),
+			Some(Pos::from_u32(7))
+		);
+
+		// Last line.
+		assert_eq!(
+			source.prev_line_start(Pos::from_u32(9)),
+			Some(Pos::from_u32(6))
+		);
+		assert_eq!(source.next_line_start(Pos::from_u32(9)), None);
+		assert_eq!(source.next_line_start(Pos::from_u32(12)), None);
+
+		assert_eq!(source.prev_line_start(Pos::from_u32(13)), None);
+		assert_eq!(source.next_line_start(Pos::from_u32(13)), None);
+	}
+
+	#[test]
+	fn line() {
+		let source = Source::new(Origin::Unknown, "Hello\nWorld\n".into());
+
+		assert_eq!(source.line_span(1), Some(Span::from(6_u32..11)));
+		assert_eq!(source.line(0), Some("Hello"));
+		assert_eq!(source.line(1), Some("World"));
+		assert_eq!(source.line(2), Some(""));
+		assert_eq!(source.line(3), None);
+	}
+
+	#[test]
+	fn ends_with_newline() {
+		assert!(Source::from_str("a\nb\n").unwrap().ends_with_newline());
+		assert!(Source::from_str("a\r\nb\r\n").unwrap().ends_with_newline());
+		assert!(Source::from_str("\n").unwrap().ends_with_newline());
+		assert!(!Source::from_str("a\nb").unwrap().ends_with_newline());
+		assert!(!Source::from_str("a\nb\r").unwrap().ends_with_newline());
+		assert!(!Source::from_str("").unwrap().ends_with_newline());
+	}
+
+	#[test]
+	fn iter_lines() {
+		let source = Source::new(Origin::Unknown, "Hello\nWorld\n".into());
+		let lines: Vec<_> = source.iter_lines().collect();
+
+		assert_eq!(lines, vec!["Hello", "World", ""]);
+		assert_eq!(source.line_count(), 3);
+
+		let source = Source::new(Origin::Unknown, String::new());
+		assert_eq!(source.iter_lines().collect::<Vec<_>>(), vec![""]);
+		assert_eq!(source.line_count(), 1);
+	}
+
+	#[test]
+	fn to_owned_lines() {
+		let source = Source::new(Origin::Unknown, "Hello\n\nWorld\n".into());
+
+		assert_eq!(source.to_owned_lines(), vec!["Hello", "", "World", ""]);
+		assert!(source.to_owned_lines().iter().eq(source.iter_lines()));
+	}
+
+	#[test]
+	fn each_line_span() {
+		let source = Source::new(Origin::Unknown, "Hello\n\nWorld\n".into());
+		let spans: Vec<_> = source.each_line_span().collect();
+
+		assert_eq!(spans.len(), source.line_count());
+		assert!(spans
+			.iter()
+			.map(|&span| &source.data()[span])
+			.eq(source.iter_lines()));
+	}
+
+	#[test]
+	fn line_spans() {
+		let source = Source::new(Origin::Unknown, "Hello\n\nWorld".into());
+		let spans = source.line_spans();
+
+		assert_eq!(spans.len(), source.line_count());
+
+		for (line, span) in spans.into_iter().enumerate() {
+			assert_eq!(source.get(span), source.line(line));
+		}
+	}
+
+	#[test]
+	fn hash_lines() {
+		let source = Source::new(Origin::Unknown, "a\nb\na\n".into());
+		let hashes = source.hash_lines();
+
+		assert_eq!(hashes.len(), source.line_count());
+		assert_eq!(hashes[0], hashes[2]);
+		assert_ne!(hashes[0], hashes[1]);
+	}
+
+	#[test]
+	fn changed_lines() {
+		let source = Source::new(
+			Origin::Unknown,
+			"fn main() {\n\tlet x = 1;\n\tx\n}\n".into(),
+		);
+		let edited = source.map(|data| data.replace("= 1", "= 2"));
+
+		assert_eq!(source.changed_lines(&edited), vec![1]);
+		assert_eq!(edited.changed_lines(&source), vec![1]);
+		assert_eq!(source.changed_lines(&source), Vec::<usize>::new());
+
+		let appended = source.map(|data| format!("{data}\n"));
+		assert_eq!(source.changed_lines(&appended), vec![5]);
+	}
+
+	#[test]
+	fn longest_line_len() {
+		let source = Source::new(
+			Origin::Unknown,
+			"fn main() {\n\tlet x = 1;\n\n}\n".into(),
+		);
+		assert_eq!(source.longest_line_len(), 11);
+
+		let source = Source::new(Origin::Unknown, "abc\nääää\nab".into());
+		assert_eq!(source.longest_line_len(), 4);
+
+		let source = Source::new(Origin::Unknown, String::new());
+		assert_eq!(source.longest_line_len(), 0);
+	}
+
+	#[test]
+	fn lines_around() {
+		let source = Source::new(Origin::Unknown, "a\nb\nc\nd\ne".into());
+
+		assert_eq!(
+			source.lines_around(Span::from(4_u32..5), 1, 1),
+			vec![(1, "b"), (2, "c"), (3, "d")]
+		);
+		assert_eq!(
+			source.lines_around(Span::from(0_u32..3), 2, 1),
+			vec![(0, "a"), (1, "b"), (2, "c")]
+		);
+		assert_eq!(
+			source.lines_around(Span::from(8_u32..9), 1, 5),
+			vec![(3, "d"), (4, "e")]
+		);
+		assert_eq!(source.lines_around(Span::from(8_u32..20), 1, 1), vec![]);
+	}
+
+	#[test]
+	fn span_of_line_range() {
+		let source = Source::new(Origin::Unknown, "l0\nl1\nl2\nl3".into());
+
+		assert_eq!(
+			source.span_of_line_range(1..3),
+			Some(Span::from(3_u32..8))
+		);
+		assert_eq!(source.span_of_line_range(1..2), source.line_span(1));
+		assert_eq!(
+			source.span_of_line_range(2..4),
+			Some(Span::from(6_u32..11))
+		);
+		assert_eq!(source.span_of_line_range(2..5), None);
+		assert_eq!(source.span_of_line_range(2..2), None);
+	}
+
+	#[test]
+	fn line_text_trimmed() {
+		let source =
+			Source::new(Origin::Unknown, "Hello\r\nWorld  \n\t \n".into());
+
+		assert_eq!(
+			source.line_text_trimmed(0),
+			Some((Span::from(0_u32..5), "Hello"))
+		);
+		assert_eq!(
+			source.line_text_trimmed(1),
+			Some((Span::from(7_u32..12), "World"))
+		);
+		assert_eq!(
+			source.line_text_trimmed(2),
+			Some((Span::from(15_u32..16), "\t"))
+		);
+		assert_eq!(source.line_text_trimmed(4), None);
+	}
+
+	#[test]
+	fn eof_span() {
+		let source = Source::new(Origin::Unknown, "Hello\nWorld\n".into());
+		assert_eq!(source.eof_span(), Span::from(12_u32..12));
+
+		let source = Source::new(Origin::Unknown, String::new());
+		assert_eq!(source.eof_span(), Span::from(0_u32..0));
+	}
 
-#[cfg(test)]
-mod tests {
 	#[test]
 	fn scan_lines() {
 		const DATA: &str = "Hello\nWorld\n";